@@ -0,0 +1,66 @@
+//! Compare drawing a large matrix with one shape per LED against drawing it as a single mesh.
+//!
+//! Run with `cargo bench --bench led_drawing`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use eframe::egui::{self, Color32, Context, Mesh, Pos2, RawInput, Rect, Rounding, Vec2};
+
+/// Size of each LED, in points
+const LED_SIZE: f32 = 8.0;
+
+/// Get the rectangle and color of every LED of a square matrix with the given sidelength
+fn leds(sidelength: usize) -> Vec<(Rect, Color32)> {
+    (0..sidelength)
+        .flat_map(|y| {
+            (0..sidelength).map(move |x| {
+                let rect = Rect::from_min_size(
+                    Pos2::new(x as f32 * LED_SIZE, y as f32 * LED_SIZE),
+                    Vec2::splat(LED_SIZE),
+                );
+                (rect, Color32::from_rgb(x as u8, y as u8, 128))
+            })
+        })
+        .collect()
+}
+
+/// Run and tessellate a single frame of egui, drawing the LEDs with the given function
+fn draw(ctx: &Context, leds: &[(Rect, Color32)], paint: fn(&egui::Painter, &[(Rect, Color32)])) {
+    let output = ctx.run(RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| paint(ui.painter(), leds));
+    });
+    ctx.tessellate(output.shapes);
+}
+
+/// Draw each LED as its own rectangle
+fn paint_rects(painter: &egui::Painter, leds: &[(Rect, Color32)]) {
+    for (rect, color) in leds {
+        painter.rect_filled(*rect, Rounding::ZERO, *color);
+    }
+}
+
+/// Draw every LED as part of one mesh
+fn paint_mesh(painter: &egui::Painter, leds: &[(Rect, Color32)]) {
+    let mut mesh = Mesh::default();
+    for (rect, color) in leds {
+        mesh.add_colored_rect(*rect, *color);
+    }
+    painter.add(mesh);
+}
+
+fn led_drawing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("led_drawing");
+    for sidelength in [16, 64, 128] {
+        let leds = leds(sidelength);
+        let ctx = Context::default();
+        group.bench_with_input(BenchmarkId::new("rects", sidelength), &leds, |b, leds| {
+            b.iter(|| draw(&ctx, leds, paint_rects))
+        });
+        group.bench_with_input(BenchmarkId::new("mesh", sidelength), &leds, |b, leds| {
+            b.iter(|| draw(&ctx, leds, paint_mesh))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, led_drawing);
+criterion_main!(benches);
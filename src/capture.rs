@@ -0,0 +1,73 @@
+use crate::color::ChannelOrder;
+use crate::raster::rasterize_frame;
+use image::RgbaImage;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Width and height of each LED in a captured image, in pixels
+pub const CAPTURE_LED_SIZE: usize = 16;
+
+/// Settings for saving every Nth frame from a plugin as a PNG image
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameCapture {
+    /// Number of frames between captures
+    pub every: u64,
+
+    /// Directory to save the captured images in, created if it doesn't exist
+    pub dir: PathBuf,
+
+    /// Order of the color channels in each LED of the captured frames
+    pub channel_order: ChannelOrder,
+}
+
+impl FrameCapture {
+    /// Returns true if the frame with the given number should be captured, counting from 1 for the first frame
+    pub fn is_due(&self, frame_number: u64) -> bool {
+        self.every > 0 && frame_number % self.every == 0
+    }
+
+    /// Save the given frame as `capture_NNNNNN.png` in the capture directory, returning the path it was saved to
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame to capture
+    /// * `frame_number` - Number of the frame, counting from 1 for the first frame of the plugin
+    pub fn capture(
+        &self,
+        frame: &[Vec<[u8; 4]>],
+        frame_number: u64,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        // Make sure the capture directory exists before saving into it
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("capture_{frame_number:06}.png"));
+        save_png(frame, self.channel_order, CAPTURE_LED_SIZE, &path)?;
+        Ok(path)
+    }
+}
+
+/// Save a frame as a PNG image, with each LED as a square of `led_size` by `led_size` pixels
+pub fn save_png(
+    frame: &[Vec<[u8; 4]>],
+    channel_order: ChannelOrder,
+    led_size: usize,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let raster = rasterize_frame(frame, channel_order, led_size);
+    let image = RgbaImage::from_raw(raster.width as u32, raster.height as u32, raster.pixels)
+        .ok_or("frame could not be converted to an image")?;
+    image.save(path)?;
+    Ok(())
+}
+
+/// Format a frame as a Rust `const` declaration named `FRAME`, one row per line, to paste into tests as a golden value
+pub fn frame_to_rust(frame: &[Vec<[u8; 4]>]) -> String {
+    let rows: String = frame
+        .iter()
+        .map(|row| {
+            let leds: Vec<String> = row.iter().map(|led| format!("{led:?}")).collect();
+            format!("    &[{}],\n", leds.join(", "))
+        })
+        .collect();
+    format!("const FRAME: &[&[[u8; 4]]] = &[\n{rows}];\n")
+}
@@ -1,29 +1,339 @@
-use clap::Parser;
+use crate::simulator_app::LETTER_SHORTCUTS;
+use clap::{Parser, ValueEnum};
+use eframe::egui::Key;
+use log::LevelFilter;
+use simtricks::color::{parse_channel_order, parse_hex_color, ChannelOrder};
+use simtricks::display_transform::{Origin, Rotation};
+use simtricks::matrix_config::Wiring;
+use simtricks::plugin_fps::is_valid_fps;
+use simtricks::test_pattern::TestPattern;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about=None)]
 pub(crate) struct SimtricksArgs {
-    /// Width of the matrix, in number of LEDs
+    /// Width of the matrix, in number of LEDs. If not given, the plugin may choose it.
     #[arg(short = 'x', long)]
-    pub width: usize,
+    pub width: Option<usize>,
 
-    /// Height of the matrix, in number of LEDs
+    /// Height of the matrix, in number of LEDs. If not given, the plugin may choose it.
     #[arg(short = 'y', long)]
-    pub height: usize,
+    pub height: Option<usize>,
 
-    /// Path to plugin
+    /// Path to plugin, or an HTTP(S) URL to download the plugin from. Give more than once to compare plugins side by side. If no plugin is given, the plugins from the last launch are opened again.
     #[arg(short, long)]
-    pub path: String,
+    pub path: Vec<String>,
+
+    /// Don't open the plugins from the last launch when no plugin is given
+    #[arg(long)]
+    pub no_restore: bool,
+
+    /// Read the plugin from stdin instead of a path, i.e. to pipe a freshly built plugin straight into the simulator
+    #[arg(long, conflicts_with = "path")]
+    pub stdin: bool,
 
     /// Number of frames per second at which to simulate the matrix
-    #[arg(short, long, default_value = "30")]
+    #[arg(short, long, default_value = "30", value_parser = parse_fps)]
     pub fps: f32,
 
+    /// Run the plugin at the frame rate it requests through `matricks_set_target_fps`, instead of the given FPS
+    #[arg(long)]
+    pub respect_plugin_fps: bool,
+
+    /// How the LEDs of the matrix are wired
+    #[arg(long, value_enum, ignore_case = true, default_value_t)]
+    pub wiring: Wiring,
+
     /// Add a host that the plugin may connect to
     #[arg(long)]
     pub allow_host: Option<Vec<String>>,
 
-    /// Map a path on the local filesystem to the plugin filesystem, as a pair of paths seperated by a greater than symbol (i.e. "LOCAL_PATH>PLUGIN_PATH")
+    /// Map a path on the local filesystem to the plugin filesystem, as a pair of paths seperated by a greater than symbol (i.e. "LOCAL_PATH>PLUGIN_PATH"). Add ":ro" to leave the original alone (i.e. "LOCAL_PATH>PLUGIN_PATH:ro"). The plugin is then given a copy of the local path, made once and shared by every plugin, and still has write access to that copy. Writes to the copy persist until Simtricks exits, even across plugin restarts, so the copy may no longer match the original. Symbolic links inside a copied directory are left out
     #[arg(long)]
     pub map_path: Option<Vec<String>>,
+
+    /// Give the plugin a variable, as a key and value separated by an equals sign (i.e. "KEY=VALUE"). Variables are put in the plugin's config, read with config_get or as environment variables, and can't replace the Matricks config.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_var)]
+    pub var: Vec<(String, String)>,
+
+    /// Maximum number of frames to keep in the history, for stepping backwards. 0 disables the history. The history uses
+    /// up to N * width * height * 4 bytes for each plugin.
+    #[arg(
+        long = "history",
+        visible_alias = "history-size",
+        default_value = "300"
+    )]
+    pub history_size: usize,
+
+    /// Number of frames to generate and discard when the plugin starts, for plugins that need time to settle
+    #[arg(long, default_value = "0")]
+    pub warmup: usize,
+
+    /// Number of recent frames to average together before showing a frame, for a motion blur effect. 1 shows each frame as is.
+    #[arg(long, default_value = "1")]
+    pub smooth: usize,
+
+    /// Redraw the window about 60 times per second, rather than only when the plugin produces a new frame
+    #[arg(long)]
+    pub continuous_repaint: bool,
+
+    /// Draw every LED as its own rectangle on every repaint, rather than uploading only the part of the matrix that changed
+    #[arg(long)]
+    pub no_dirty_tracking: bool,
+
+    /// Save every Nth frame from the plugin as a PNG image in the capture directory
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub capture_every: Option<u64>,
+
+    /// Directory to save captured frames in, created if it doesn't exist
+    #[arg(long, default_value = "captures")]
+    pub capture_dir: PathBuf,
+
+    /// Rotate the displayed matrix clockwise by the given number of degrees
+    #[arg(long, value_enum, default_value_t)]
+    pub rotate: Rotation,
+
+    /// Mirror the displayed matrix horizontally, reversing the order of the columns
+    #[arg(long, visible_alias = "flip-x")]
+    pub flip_h: bool,
+
+    /// Mirror the displayed matrix vertically, reversing the order of the rows
+    #[arg(long, visible_alias = "flip-y")]
+    pub flip_v: bool,
+
+    /// Corner of the matrix where the first LED of each frame is found
+    #[arg(long, value_enum, ignore_case = true, default_value_t)]
+    pub origin: Origin,
+
+    /// Freeze the simulator if the plugin fails to set up, instead of running it anyway
+    #[arg(long)]
+    pub strict_setup: bool,
+
+    /// Stop the plugin if it returns a frame that doesn't fit the matrix, instead of padding or trimming the frame
+    #[arg(long)]
+    pub strict_dimensions: bool,
+
+    /// Pause the plugin when an update fails, rather than stopping it, so that it can be stepped to try again
+    #[arg(long)]
+    pub pause_on_error: bool,
+
+    /// When comparing plugins, show each plugin in its own tab with its own controls, instead of side by side
+    #[arg(long)]
+    pub tabs: bool,
+
+    /// Stream each new frame to WebSocket clients on the given local port, as JSON
+    #[arg(long)]
+    pub ws_port: Option<u16>,
+
+    /// When comparing two plugins, show a third matrix highlighting the LEDs where they differ
+    #[arg(long, conflicts_with = "tabs")]
+    pub diff: bool,
+
+    /// Largest difference between the channels of two LEDs for them to be considered matching, when using --diff
+    #[arg(long, default_value = "0", requires = "diff")]
+    pub diff_threshold: u8,
+
+    /// When using --diff, show only the difference, with each channel of each LED showing how different that channel is, scaled by 4
+    #[arg(long, requires = "diff")]
+    pub diff_overlay: bool,
+
+    /// Run the plugin without a window, writing each frame to stdout as a line of JSON
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Run the plugin without a window for a single update, saving the frame as a PNG image at the given path
+    #[arg(long, value_name = "OUTPUT.png", conflicts_with = "headless")]
+    pub once: Option<PathBuf>,
+
+    /// Number of frames to write before exiting, when running headless. If not given, run until the plugin is done.
+    #[arg(long, requires = "headless")]
+    pub frames: Option<usize>,
+
+    /// When running headless, write every frame to the given file as a single JSON array instead, for --play-frames
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "headless",
+        conflicts_with = "once"
+    )]
+    pub dump_frames: Option<PathBuf>,
+
+    /// Play frames dumped with --dump-frames, without the plugin that produced them
+    #[arg(long, value_name = "FILE")]
+    pub play_frames: Option<PathBuf>,
+
+    /// Run the plugin without a window, writing frames to the given file as raw binary: each frame is a 4 byte little-endian frame index, followed by width * height * 4 bytes of LEDs in BGRA order
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["headless", "once"], requires = "export_count")]
+    pub export_raw: Option<PathBuf>,
+
+    /// Number of frames to write with --export-raw, unless the plugin is done sooner
+    #[arg(long, value_name = "N", requires = "export_raw")]
+    pub export_count: Option<usize>,
+
+    /// Print the configuration that would be passed to the plugin as JSON, and exit without running the plugin
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Restart the plugin without asking for confirmation
+    #[arg(long)]
+    pub no_restart_confirmation: bool,
+
+    /// Keep showing the last frame on restart until the restarted plugin produces a frame, instead of clearing the matrix
+    #[arg(long)]
+    pub keep_frame_on_restart: bool,
+
+    /// Aspect ratio of a single LED, as a width and height separated by a colon (i.e. "3:1")
+    #[arg(long, visible_alias = "cell-aspect", default_value = "1:1", value_parser = parse_aspect_ratio)]
+    pub led_aspect: (u32, u32),
+
+    /// Order of the color channels in each LED of the frames produced by the plugin (i.e. "BGRA", "RGBA", "RGB", "GRB")
+    #[arg(long, default_value = "BGRA", value_parser = parse_channel_order)]
+    pub channel_order: ChannelOrder,
+
+    /// Blend each LED over a background of the given hex color (i.e. "#1E1E1E"), to preview how transparent LEDs look
+    #[arg(long, value_parser = parse_hex_color)]
+    pub background: Option<[u8; 3]>,
+
+    /// JSON file of lookup tables that remap the displayed red, green, and blue values, to simulate the color cast of a
+    /// panel. Holds {"red": [...], "green": [...], "blue": [...]}, with 256 values from 0 to 255 in each list.
+    #[arg(long, value_name = "PATH")]
+    pub lut: Option<PathBuf>,
+
+    /// How to display the alpha channel of each LED. Can be changed while running with the A key.
+    #[arg(long, value_enum, default_value = "premultiplied")]
+    pub alpha_mode: AlphaMode,
+
+    /// Letter key that saves the state of the plugin to snapshot.bin in the capture directory, for plugins that export serialize_state. L restores it.
+    #[arg(long, default_value = "S", value_parser = parse_snapshot_key)]
+    pub snapshot_key: Key,
+
+    /// Log level of Simtricks and the plugin. If not given, Simtricks logs at the info level and the plugin logs at the debug level.
+    #[arg(long, value_enum)]
+    pub log_level: Option<LogLevel>,
+
+    /// Log everything, including how long each plugin update takes. Same as --log-level trace.
+    #[arg(short, long, conflicts_with_all = ["quiet", "log_level"])]
+    pub verbose: bool,
+
+    /// Only log warnings and errors. Same as --log-level warn.
+    #[arg(short, long, conflicts_with = "log_level")]
+    pub quiet: bool,
+
+    /// Write each log to stderr as a line of JSON, for log aggregators that expect structured output
+    #[arg(long)]
+    pub json_log: bool,
+
+    /// File to append each log made by the plugin to, as lines of the form "[LEVEL] message". Created if it doesn't exist.
+    #[arg(long, value_name = "PATH")]
+    pub plugin_log_file: Option<PathBuf>,
+
+    /// Show a built-in test pattern, without a plugin or alongside the plugins given
+    #[arg(long, value_enum, ignore_case = true, value_name = "NAME")]
+    pub test_pattern: Option<TestPattern>,
+}
+
+/// Parse an aspect ratio of the form "W:H", where both W and H are positive integers
+fn parse_aspect_ratio(aspect: &str) -> Result<(u32, u32), String> {
+    let (width, height) = aspect
+        .split_once(':')
+        .ok_or_else(|| String::from("expected a ratio of the form W:H"))?;
+    let width: u32 = width.trim().parse().map_err(|e| format!("{e}"))?;
+    let height: u32 = height.trim().parse().map_err(|e| format!("{e}"))?;
+    if width == 0 || height == 0 {
+        return Err(String::from("both sides of the ratio must be at least 1"));
+    }
+    Ok((width, height))
+}
+
+/// Parse a frame rate, refusing any that can't be run at
+fn parse_fps(fps: &str) -> Result<f32, String> {
+    let fps: f32 = fps.trim().parse().map_err(|e| format!("{e}"))?;
+    if !is_valid_fps(fps) {
+        return Err(String::from(
+            "expected a finite number of frames per second above 0",
+        ));
+    }
+    Ok(fps)
+}
+
+/// Parse a variable for the plugin, of the form KEY=VALUE
+fn parse_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(String::from("expected a variable of the form KEY=VALUE")),
+    }
+}
+
+/// Parse a single letter into a key, refusing letters that already have a shortcut
+fn parse_snapshot_key(key: &str) -> Result<Key, String> {
+    let letter = key.trim().to_ascii_uppercase();
+    if letter.len() != 1 || !letter.chars().all(|letter| letter.is_ascii_alphabetic()) {
+        return Err(String::from("expected a single letter"));
+    }
+
+    // Look the key up by its name, as egui 0.23 has no Key::from_name but names each letter key after its letter
+    let key: Key = serde_json::from_value(serde_json::Value::String(letter))
+        .map_err(|e| format!("unknown key: {e}"))?;
+    if LETTER_SHORTCUTS.contains(&key) {
+        return Err(format!("'{}' is already a shortcut", key.name()));
+    }
+    Ok(key)
+}
+
+/// How the alpha channel of each LED is displayed
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum AlphaMode {
+    /// Treat the color channels as already multiplied by alpha
+    #[default]
+    Premultiplied,
+
+    /// Treat the color channels as not yet multiplied by alpha
+    Straight,
+
+    /// Draw every LED as opaque
+    Ignore,
+}
+
+impl AlphaMode {
+    /// Get the mode after this one, wrapping around after the last mode
+    pub(crate) fn next(self) -> Self {
+        match self {
+            AlphaMode::Premultiplied => AlphaMode::Straight,
+            AlphaMode::Straight => AlphaMode::Ignore,
+            AlphaMode::Ignore => AlphaMode::Premultiplied,
+        }
+    }
+
+    /// Get the name of this mode, as given on the command line
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            AlphaMode::Premultiplied => "premultiplied",
+            AlphaMode::Straight => "straight",
+            AlphaMode::Ignore => "ignore",
+        }
+    }
+}
+
+/// Verbosity of the logger
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
 }
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Build a lookup table that applies gamma correction to a color channel
+///
+/// Each channel value `c` maps to `255 * (c / 255) ^ gamma`, so a gamma above 1 darkens the midtones, much like the
+/// gamma correction applied to a real LED panel.
+pub fn gamma_table(gamma: f32) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (value, corrected) in table.iter_mut().enumerate() {
+        *corrected = ((value as f32 / 255.0).powf(gamma) * 255.0).round() as u8;
+    }
+    table
+}
+
+/// Lookup tables that remap the value of each of the red, green, and blue channels, in that order
+pub type ColorLut = [[u8; 256]; 3];
+
+/// Load lookup tables for the red, green, and blue channels from a JSON file
+///
+/// The file must hold an object of the form `{"red": [...], "green": [...], "blue": [...]}`, where each list has 256
+/// values from 0 to 255, giving the displayed value of each channel value in turn.
+pub fn load_lut(path: &Path) -> Result<ColorLut, Box<dyn Error>> {
+    let lut: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    let mut tables = [[0; 256]; 3];
+    for (table, channel) in tables.iter_mut().zip(["red", "green", "blue"]) {
+        let values = lut
+            .get(channel)
+            .and_then(serde_json::Value::as_array)
+            .ok_or(format!("missing a list of {channel} values"))?;
+        if values.len() != table.len() {
+            return Err(format!(
+                "expected {} {channel} values, but found {}",
+                table.len(),
+                values.len()
+            )
+            .into());
+        }
+        for (entry, value) in table.iter_mut().zip(values) {
+            *entry = value
+                .as_u64()
+                .and_then(|value| u8::try_from(value).ok())
+                .ok_or(format!("{channel} value {value} is not between 0 and 255"))?;
+        }
+    }
+    Ok(tables)
+}
+
+/// Index of each channel within an LED of a frame, in the order red, green, blue, alpha
+pub type ChannelOrder = [usize; 4];
+
+/// Channel order of frames produced by Matricks plugins
+pub const DEFAULT_CHANNEL_ORDER: ChannelOrder = [2, 1, 0, 3];
+
+/// Parse a color given as hex, such as "#1E1E1E" or "1e1e1e", into its red, green, and blue channels
+pub fn parse_hex_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(String::from("expected a hex color such as #1E1E1E"));
+    }
+
+    let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap();
+    Ok([channel(0), channel(1), channel(2)])
+}
+
+/// Composite a premultiplied RGBA color over an opaque background, giving the opaque RGB color that results
+pub fn blend_over(color: [u8; 4], background: [u8; 3]) -> [u8; 3] {
+    let transparency = 255 - color[3] as u32;
+    let mut blended = [0; 3];
+    for (channel, blended) in blended.iter_mut().enumerate() {
+        let shown = color[channel] as u32 + (background[channel] as u32 * transparency + 127) / 255;
+        *blended = shown.min(255) as u8;
+    }
+    blended
+}
+
+/// Parse a channel order such as "BGRA" or "GRB" into the index of each channel within an LED
+///
+/// Each of R, G, and B must appear exactly once. If A is left out, alpha is taken from the last byte of the LED.
+pub fn parse_channel_order(order: &str) -> Result<ChannelOrder, String> {
+    let order = order.trim().to_ascii_uppercase();
+    if !(3..=4).contains(&order.len()) {
+        return Err(String::from(
+            "expected 3 or 4 channels, such as BGRA or RGB",
+        ));
+    }
+
+    // Find the position of each channel in the order
+    let mut indices = [None; 4];
+    for (index, channel) in order.chars().enumerate() {
+        let slot = match channel {
+            'R' => 0,
+            'G' => 1,
+            'B' => 2,
+            'A' => 3,
+            _ => return Err(format!("unknown channel '{channel}'")),
+        };
+        if indices[slot].replace(index).is_some() {
+            return Err(format!("channel '{channel}' is given more than once"));
+        }
+    }
+
+    // Alpha defaults to the last byte, which is unused by a three channel order
+    Ok([
+        indices[0].ok_or("missing the R channel")?,
+        indices[1].ok_or("missing the G channel")?,
+        indices[2].ok_or("missing the B channel")?,
+        indices[3].unwrap_or(3),
+    ])
+}
+
+/// Get how different each channel of each LED of two frames is, as the absolute difference between the channels
+///
+/// LEDs missing from either frame are treated as blank.
+pub fn channel_difference(a: &[Vec<[u8; 4]>], b: &[Vec<[u8; 4]>]) -> Vec<Vec<[u8; 4]>> {
+    let led = |frame: &[Vec<[u8; 4]>], x: usize, y: usize| {
+        frame
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or([0; 4])
+    };
+    let height = a.len().max(b.len());
+    (0..height)
+        .map(|y| {
+            let width = [a, b]
+                .iter()
+                .map(|frame| frame.get(y).map_or(0, |row| row.len()))
+                .max()
+                .unwrap_or(0);
+            (0..width)
+                .map(|x| {
+                    let (led_a, led_b) = (led(a, x, y), led(b, x, y));
+                    [0, 1, 2, 3].map(|channel| led_a[channel].abs_diff(led_b[channel]))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Average several frames of the same dimensions, LED by LED and channel by channel, rounding to the nearest value
+///
+/// Returns an empty frame if no frames are given.
+pub fn average_frames<'a>(
+    frames: impl IntoIterator<Item = &'a Vec<Vec<[u8; 4]>>>,
+) -> Vec<Vec<[u8; 4]>> {
+    let mut frames = frames.into_iter().peekable();
+    let Some(first) = frames.peek() else {
+        return vec![];
+    };
+
+    // Sum every channel of every LED, counting the frames as we go
+    let mut sums: Vec<Vec<[u32; 4]>> = first.iter().map(|row| vec![[0; 4]; row.len()]).collect();
+    let mut count = 0;
+    for frame in frames {
+        for (sum_row, row) in sums.iter_mut().zip(frame) {
+            for (sum, led) in sum_row.iter_mut().zip(row) {
+                for channel in 0..4 {
+                    sum[channel] += led[channel] as u32;
+                }
+            }
+        }
+        count += 1;
+    }
+
+    sums.iter()
+        .map(|row| {
+            row.iter()
+                .map(|sum| sum.map(|channel| ((channel + count / 2) / count) as u8))
+                .collect()
+        })
+        .collect()
+}
+
+/// Format the color of an LED as a `#RRGGBB` hex string, leaving out alpha
+pub fn led_hex(led: &[u8; 4], channel_order: ChannelOrder) -> String {
+    let [red, green, blue, _] = channel_order.map(|channel| led[channel]);
+    format!("#{red:02X}{green:02X}{blue:02X}")
+}
+
+/// Get the most common colors of a frame as `#RRGGBBAA` hex strings, most common first, up to the given count
+///
+/// Colors that appear equally often are ordered by their hex string.
+pub fn palette(frame: &[Vec<[u8; 4]>], channel_order: ChannelOrder, count: usize) -> Vec<String> {
+    most_common_colors(frame, channel_order, count)
+        .into_iter()
+        .map(|([red, green, blue, alpha], _)| format!("#{red:02X}{green:02X}{blue:02X}{alpha:02X}"))
+        .collect()
+}
+
+/// Get the most common colors of a frame in RGBA order, with the number of LEDs of each color, most common first, up to
+/// the given count
+///
+/// Colors that appear equally often are ordered by their RGBA values.
+pub fn most_common_colors(
+    frame: &[Vec<[u8; 4]>],
+    channel_order: ChannelOrder,
+    count: usize,
+) -> Vec<([u8; 4], usize)> {
+    // Count how many LEDs have each color
+    let mut frequencies: HashMap<[u8; 4], usize> = HashMap::new();
+    for led in frame.iter().flatten() {
+        *frequencies.entry(*led).or_default() += 1;
+    }
+
+    let [red, green, blue, alpha] = channel_order;
+    let mut colors: Vec<([u8; 4], usize)> = frequencies
+        .into_iter()
+        .map(|(led, frequency)| ([led[red], led[green], led[blue], led[alpha]], frequency))
+        .collect();
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    colors.truncate(count);
+    colors
+}
+
+/// Lowest, highest, and mean value of one color channel across a frame
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ChannelStats {
+    /// Lowest value of the channel in any LED
+    pub min: u8,
+
+    /// Highest value of the channel in any LED
+    pub max: u8,
+
+    /// Mean value of the channel over every LED
+    pub mean: f32,
+}
+
+/// Get the lowest, highest, and mean values of the red, green, and blue channels of a frame, in that order
+///
+/// An empty frame gives zero for every value.
+pub fn channel_stats(frame: &[Vec<[u8; 4]>], channel_order: ChannelOrder) -> [ChannelStats; 3] {
+    let [red, green, blue, _] = channel_order;
+    [red, green, blue].map(|channel| {
+        let mut values = frame.iter().flatten().map(|led| led[channel]);
+        let Some(first) = values.next() else {
+            return ChannelStats::default();
+        };
+
+        let (min, max, sum, count) = values.fold(
+            (first, first, first as u64, 1u64),
+            |(min, max, sum, count), value| {
+                (
+                    min.min(value),
+                    max.max(value),
+                    sum + value as u64,
+                    count + 1,
+                )
+            },
+        );
+        ChannelStats {
+            min,
+            max,
+            mean: sum as f32 / count as f32,
+        }
+    })
+}
+
+/// Count the LEDs of a frame in each bucket of colors, ignoring alpha
+///
+/// Each of the red, green, and blue channels is split into `levels` equal ranges, giving `levels³` buckets. The bucket
+/// of an LED is at index `(r * levels + g) * levels + b`, where `r`, `g`, and `b` are the ranges of its channels.
+pub fn color_histogram(
+    frame: &[Vec<[u8; 4]>],
+    channel_order: ChannelOrder,
+    levels: usize,
+) -> Vec<usize> {
+    let levels = levels.clamp(1, 256);
+    let [red, green, blue, _] = channel_order;
+    let range = |channel: u8| channel as usize * levels / 256;
+
+    let mut counts = vec![0; levels * levels * levels];
+    for led in frame.iter().flatten() {
+        counts[(range(led[red]) * levels + range(led[green])) * levels + range(led[blue])] += 1;
+    }
+    counts
+}
+
+/// Get the color in the middle of a bucket of a color histogram with the given number of levels
+pub fn histogram_bucket_color(bucket: usize, levels: usize) -> [u8; 3] {
+    let levels = levels.clamp(1, 256);
+    let middle = |range: usize| ((range * 256 + 128) / levels).min(255) as u8;
+    [
+        middle(bucket / (levels * levels) % levels),
+        middle(bucket / levels % levels),
+        middle(bucket % levels),
+    ]
+}
+
+/// Spread the light of each LED onto its neighbors, for a soft glow like the light bleed of a real LED panel
+///
+/// Each LED of the result keeps part of its own color, and gains a fainter part of the colors of the eight LEDs around
+/// it, with diagonal neighbors contributing least. Every channel is treated alike, and saturates at 255.
+pub fn bloom_frame(frame: &[Vec<[u8; 4]>]) -> Vec<Vec<[u8; 4]>> {
+    const SELF_WEIGHT: f32 = 0.5;
+    const EDGE_WEIGHT: f32 = 0.15;
+    const DIAGONAL_WEIGHT: f32 = 0.07;
+
+    frame
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            (0..row.len())
+                .map(|x| {
+                    let mut glow = [0.0; 4];
+                    for dy in -1..=1_isize {
+                        for dx in -1..=1_isize {
+                            let neighbor = y
+                                .checked_add_signed(dy)
+                                .and_then(|y| frame.get(y))
+                                .and_then(|row| x.checked_add_signed(dx).and_then(|x| row.get(x)));
+                            let Some(neighbor) = neighbor else {
+                                continue;
+                            };
+                            let weight = match (dx, dy) {
+                                (0, 0) => SELF_WEIGHT,
+                                (0, _) | (_, 0) => EDGE_WEIGHT,
+                                _ => DIAGONAL_WEIGHT,
+                            };
+                            for channel in 0..4 {
+                                glow[channel] += neighbor[channel] as f32 * weight;
+                            }
+                        }
+                    }
+                    glow.map(|channel| channel.round().min(255.0) as u8)
+                })
+                .collect()
+        })
+        .collect()
+}
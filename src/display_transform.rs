@@ -0,0 +1,177 @@
+use clap::ValueEnum;
+
+/// Corner of the physical matrix where the first LED of a frame is found
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Origin {
+    /// The first LED is in the top left corner, and the rows run left to right from the top
+    #[default]
+    TopLeft,
+
+    /// The first LED is in the top right corner, and the rows run right to left from the top
+    TopRight,
+
+    /// The first LED is in the bottom left corner, and the rows run left to right from the bottom
+    BottomLeft,
+
+    /// The first LED is in the bottom right corner, and the rows run right to left from the bottom
+    BottomRight,
+}
+
+impl Origin {
+    /// Returns true if the columns of a frame run right to left from this origin
+    pub fn flips_x(self) -> bool {
+        matches!(self, Origin::TopRight | Origin::BottomRight)
+    }
+
+    /// Returns true if the rows of a frame run bottom to top from this origin
+    pub fn flips_y(self) -> bool {
+        matches!(self, Origin::BottomLeft | Origin::BottomRight)
+    }
+}
+
+/// Clockwise rotation of the displayed matrix, named by its number of degrees
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    None,
+    #[value(name = "90")]
+    Clockwise90,
+    #[value(name = "180")]
+    Clockwise180,
+    #[value(name = "270")]
+    Clockwise270,
+}
+
+impl Rotation {
+    /// Get the rotation for the given number of degrees clockwise, if it is a multiple of 90
+    pub fn from_degrees(degrees: u16) -> Option<Self> {
+        match degrees % 360 {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Clockwise90),
+            180 => Some(Rotation::Clockwise180),
+            270 => Some(Rotation::Clockwise270),
+            _ => None,
+        }
+    }
+
+    /// Get the number of degrees clockwise of this rotation
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 90,
+            Rotation::Clockwise180 => 180,
+            Rotation::Clockwise270 => 270,
+        }
+    }
+
+    /// Returns true if this rotation swaps the width and height of the matrix
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Clockwise90 | Rotation::Clockwise270)
+    }
+}
+
+/// A transform applied to the matrix when it is displayed, such as for a panel that is mounted rotated
+///
+/// The frame is first moved to its origin, then flipped, and then rotated. This transform only affects how the matrix is
+/// displayed, and never the data that is passed to or received from the plugin.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DisplayTransform {
+    /// Clockwise rotation of the matrix
+    pub rotation: Rotation,
+
+    /// If true, mirror the matrix horizontally
+    pub flip_h: bool,
+
+    /// If true, mirror the matrix vertically
+    pub flip_v: bool,
+
+    /// Corner of the matrix where the first LED of a frame is found
+    pub origin: Origin,
+}
+
+impl DisplayTransform {
+    /// Returns true if the matrix is mirrored horizontally, by the flip and origin together
+    fn flips_x(&self) -> bool {
+        self.flip_h != self.origin.flips_x()
+    }
+
+    /// Returns true if the matrix is mirrored vertically, by the flip and origin together
+    fn flips_y(&self) -> bool {
+        self.flip_v != self.origin.flips_y()
+    }
+
+    /// Get the dimensions of the displayed matrix, given the dimensions of the frame (width, then height)
+    pub fn displayed_dimensions(&self, dimensions: (usize, usize)) -> (usize, usize) {
+        if self.rotation.is_sideways() {
+            (dimensions.1, dimensions.0)
+        } else {
+            dimensions
+        }
+    }
+
+    /// Get the position in the frame of the LED displayed at the given position
+    ///
+    /// # Arguments
+    ///
+    /// * `displayed` - Position of the LED on the displayed matrix (x, then y)
+    /// * `dimensions` - Dimensions of the frame (width, then height)
+    pub fn frame_position(
+        &self,
+        displayed: (usize, usize),
+        dimensions: (usize, usize),
+    ) -> (usize, usize) {
+        let (dx, dy) = displayed;
+        let (width, height) = dimensions;
+
+        // Undo the rotation
+        let (x, y) = match self.rotation {
+            Rotation::None => (dx, dy),
+            Rotation::Clockwise90 => (dy, height - 1 - dx),
+            Rotation::Clockwise180 => (width - 1 - dx, height - 1 - dy),
+            Rotation::Clockwise270 => (width - 1 - dy, dx),
+        };
+
+        // Undo the flips and origin
+        let x = if self.flips_x() { width - 1 - x } else { x };
+        let y = if self.flips_y() { height - 1 - y } else { y };
+
+        (x, y)
+    }
+
+    /// Transform a frame into the frame that should be displayed, looking up each displayed LED in a single pass
+    ///
+    /// Rows shorter than the first row are padded with blank LEDs.
+    pub fn apply(&self, frame: &[Vec<[u8; 4]>]) -> Vec<Vec<[u8; 4]>> {
+        let dimensions = (frame.first().map_or(0, |row| row.len()), frame.len());
+        let (width, height) = self.displayed_dimensions(dimensions);
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        let (frame_x, frame_y) = self.frame_position((x, y), dimensions);
+                        frame
+                            .get(frame_y)
+                            .and_then(|row| row.get(frame_x))
+                            .copied()
+                            .unwrap_or([0; 4])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Turn this transform into a function applying it to frames
+    ///
+    /// A transform that has no effect copies frames as they are, without looking up each LED.
+    pub fn chain(&self) -> FrameTransform {
+        let transform = *self;
+        if self.rotation == Rotation::None && !self.flips_x() && !self.flips_y() {
+            return Box::new(|frame| frame.to_vec());
+        }
+        Box::new(move |frame| transform.apply(frame))
+    }
+}
+
+/// A function transforming a frame into the frame that should be displayed
+pub type FrameTransform = Box<dyn Fn(&[Vec<[u8; 4]>]) -> Vec<Vec<[u8; 4]>> + Send + Sync>;
@@ -0,0 +1,59 @@
+use crate::plugin_thread::FrameSource;
+use crate::Frame;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Load frames dumped with `headless::dump_frames`, as a JSON array of frames
+pub fn load_frames(path: &Path) -> Result<Vec<Frame>, Box<dyn Error>> {
+    let frames: Vec<Frame> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if frames.is_empty() {
+        return Err(format!("{} doesn't contain any frames.", path.display()).into());
+    }
+    Ok(frames)
+}
+
+/// Plays dumped frames in place of a plugin, from a plugin thread
+pub struct FramePlayer {
+    /// The frames to play, in order
+    frames: Vec<Frame>,
+
+    /// Index of the next frame to play
+    next: usize,
+}
+
+impl FramePlayer {
+    /// Create a player for the given frames, starting from the first frame
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Self { frames, next: 0 }
+    }
+
+    /// Get the width and height of the first frame, in number of LEDs
+    pub fn dimensions(&self) -> Option<(usize, usize)> {
+        let first = self.frames.first()?;
+        Some((first.first().map_or(0, Vec::len), first.len()))
+    }
+}
+
+impl FrameSource for FramePlayer {
+    fn setup(&mut self) -> Result<(), Box<dyn Error>> {
+        log::info!("Playing {} dumped frames.", self.frames.len());
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<Option<Frame>, Box<dyn Error>> {
+        let frame = self.frames.get(self.next).cloned();
+        self.next += 1;
+        Ok(frame)
+    }
+
+    fn save_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.next.to_string())?;
+        Ok(())
+    }
+
+    fn load_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.next = fs::read_to_string(path)?.trim().parse()?;
+        Ok(())
+    }
+}
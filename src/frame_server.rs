@@ -0,0 +1,189 @@
+use crate::{Frame, SharedFrame};
+use serde_json::json;
+use std::error::Error;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tungstenite::{accept, Message, WebSocket};
+
+/// How often the server checks for a new frame
+const FRAME_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long to wait for a client to accept a frame before disconnecting it
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A WebSocket server that broadcasts frames to connected clients, accepting clients and broadcasting from threads of
+/// its own
+///
+/// Whenever the frame changes, each client is sent a text message holding a JSON object of the form
+/// `{"frame": [[...]], "index": N, "ts_ms": X}`, the same as the lines written when running headless. `index` counts
+/// broadcast frames from zero, and `ts_ms` is the number of milliseconds since the server started.
+pub struct FrameServer {
+    /// Local port that the server is listening on
+    port: u16,
+
+    /// If true, the server threads should quit
+    stop: Arc<AtomicBool>,
+
+    /// The threads accepting clients and broadcasting frames, if the server hasn't been shut down
+    handles: Option<(JoinHandle<()>, JoinHandle<()>)>,
+}
+
+impl FrameServer {
+    /// Start a server on the given local port, broadcasting the given shared frame whenever it is replaced
+    ///
+    /// If the port is zero, the operating system picks a free port, which can be found with `port`.
+    pub fn start(port: u16, frame: SharedFrame) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        log::info!("Streaming frames to WebSocket clients at ws://127.0.0.1:{port}.");
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let accept_handle = {
+            let (clients, stop) = (Arc::clone(&clients), Arc::clone(&stop));
+            thread::spawn(move || accept_clients(listener, clients, stop))
+        };
+        let broadcast_handle = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || broadcast(frame, clients, stop))
+        };
+
+        Ok(Self {
+            port,
+            stop,
+            handles: Some((accept_handle, broadcast_handle)),
+        })
+    }
+
+    /// Get the local port that the server is listening on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Stop the server, waiting for its threads to quit
+    pub fn shutdown(&mut self) {
+        if let Some((accept_handle, broadcast_handle)) = self.handles.take() {
+            self.stop.store(true, Ordering::SeqCst);
+
+            // Wake the accepting thread, which is blocked until a client connects
+            let _ = TcpStream::connect(("127.0.0.1", self.port));
+            let accept_result = accept_handle.join();
+            let broadcast_result = broadcast_handle.join();
+            if accept_result.is_err() || broadcast_result.is_err() {
+                log::warn!("WebSocket server thread panicked.");
+            }
+            log::info!("Stopped WebSocket server.");
+        }
+    }
+}
+
+impl Drop for FrameServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Clients connected to the server, and the last message broadcast to them
+#[derive(Default)]
+struct Clients {
+    /// Every connected client
+    sockets: Vec<WebSocket<TcpStream>>,
+
+    /// Last message broadcast, which is sent to new clients straight away
+    last_message: Option<String>,
+}
+
+/// Accept new clients until told to stop, blocking until each one connects
+///
+/// The handshake happens on this thread, so a slow client never holds up the frames sent to the others.
+fn accept_clients(listener: TcpListener, clients: Arc<Mutex<Clients>>, stop: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let address = stream.peer_addr().map_or_else(
+            |_| String::from("an unknown address"),
+            |address| address.to_string(),
+        );
+
+        // Complete the handshake, then send the last frame straight away
+        let result = handshake(stream).and_then(|mut client| {
+            let mut clients = clients.lock().unwrap();
+            if let Some(message) = &clients.last_message {
+                client.send(Message::text(message.clone()))?;
+            }
+            clients.sockets.push(client);
+            Ok(())
+        });
+        match result {
+            Ok(_) => log::info!("WebSocket client connected from {address}."),
+            Err(e) => {
+                log::warn!("Failed to accept WebSocket client from {address}.");
+                log::debug!("WebSocket handshake failed with the following error: {e}");
+            }
+        }
+    }
+}
+
+/// Broadcast new frames to the connected clients, until told to stop
+fn broadcast(shared_frame: SharedFrame, clients: Arc<Mutex<Clients>>, stop: Arc<AtomicBool>) {
+    let time_at_start = Instant::now();
+    let mut last_frame: Option<Arc<Frame>> = None;
+    let mut index: u64 = 0;
+
+    while !stop.load(Ordering::SeqCst) {
+        // Broadcast the frame if it has changed, dropping any clients that can't receive it. Only hold the lock long
+        // enough to copy the pointer, and skip comparing the contents if the same frame is still shared.
+        let frame = Arc::clone(&shared_frame.lock().unwrap());
+        let changed = match &last_frame {
+            Some(last_frame) => !Arc::ptr_eq(last_frame, &frame) && **last_frame != *frame,
+            None => true,
+        };
+        if changed {
+            let message = json!({
+                "frame": *frame,
+                "index": index,
+                "ts_ms": time_at_start.elapsed().as_millis() as u64,
+            })
+            .to_string();
+            let mut clients = clients.lock().unwrap();
+            clients.sockets.retain_mut(|client| {
+                match client.send(Message::text(message.clone())) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        log::info!("WebSocket client disconnected.");
+                        log::debug!(
+                        "Failed to send frame to WebSocket client with the following error: {e}"
+                    );
+                        false
+                    }
+                }
+            });
+            clients.last_message = Some(message);
+            last_frame = Some(frame);
+            index += 1;
+        }
+
+        thread::sleep(FRAME_POLL_INTERVAL);
+    }
+
+    // Say goodbye to any remaining clients
+    for mut client in clients.lock().unwrap().sockets.drain(..) {
+        let _ = client.close(None);
+        let _ = client.flush();
+    }
+}
+
+/// Complete the WebSocket handshake with a new client
+fn handshake(stream: TcpStream) -> Result<WebSocket<TcpStream>, Box<dyn Error>> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.set_read_timeout(Some(WRITE_TIMEOUT))?;
+    Ok(accept(stream)?)
+}
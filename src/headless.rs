@@ -0,0 +1,228 @@
+use crate::capture::{save_png, CAPTURE_LED_SIZE};
+use crate::color::ChannelOrder;
+use crate::plugin::{
+    create_plugin, next_frame, sandbox_hint, setup_plugin, PluginOptions, UpdateError,
+};
+use crate::Frame;
+use extism::Plugin;
+use serde_json::json;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Frames pulled from a plugin running without a GUI, one per update, until the plugin signals that it is done
+///
+/// Frames are pulled as fast as they are asked for. After an error, no more frames are pulled.
+struct HeadlessFrames {
+    /// The plugin to pull frames from
+    plugin: Plugin<'static>,
+
+    /// If true, the plugin is done or has failed, so no more frames are pulled
+    done: bool,
+}
+
+impl HeadlessFrames {
+    /// Create and set up a plugin, ready to pull frames from
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options used to create the plugin
+    fn new(options: &PluginOptions) -> Result<Self, Box<dyn Error>> {
+        let mut plugin = create_plugin(options)?;
+
+        // A failed setup is logged, and the plugin is run anyway
+        let _ = setup_plugin(&mut plugin);
+
+        Ok(HeadlessFrames {
+            plugin,
+            done: false,
+        })
+    }
+}
+
+impl Iterator for HeadlessFrames {
+    type Item = Result<Frame, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match next_frame(&mut self.plugin) {
+            Ok(Some(frame)) => Some(Ok(frame)),
+            Ok(None) => {
+                log::info!("Plugin has stopped providing updates.");
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                let error = describe_update_error(e);
+
+                // Point out how to allow a refused host, as the plugin can't explain it itself
+                if let Some(hint) = sandbox_hint(&error.to_string()) {
+                    log::warn!("{hint}");
+                }
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Run a plugin without a GUI, collecting the frame produced at each step
+///
+/// The plugin is set up, and then updated up to `steps` times. If the plugin signals that it is done before `steps`
+/// frames have been produced, only the frames produced so far are returned.
+///
+/// # Arguments
+///
+/// * `options` - Options used to create the plugin
+/// * `steps` - Maximum number of frames to pull from the plugin
+pub fn run_frames(options: &PluginOptions, steps: usize) -> Result<Vec<Frame>, Box<dyn Error>> {
+    HeadlessFrames::new(options)?.take(steps).collect()
+}
+
+/// Run a plugin without a GUI for a single update, saving the frame it produces as a PNG image
+///
+/// Each LED is saved as a square of the same size as in captured frames. Fails if the plugin signals that it is done
+/// instead of producing a frame.
+///
+/// # Arguments
+///
+/// * `options` - Options used to create the plugin
+/// * `channel_order` - Order of the color channels in each LED of the frame
+/// * `path` - Where to save the image
+pub fn save_single_frame(
+    options: &PluginOptions,
+    channel_order: ChannelOrder,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let frame = run_frames(options, 1)?
+        .pop()
+        .ok_or("plugin did not produce a frame on its first update")?;
+    save_png(&frame, channel_order, CAPTURE_LED_SIZE, path)
+}
+
+/// Run a plugin without a GUI, writing each frame to `output` as a line of JSON
+///
+/// Each line is a JSON object of the form `{"frame": [[...]], "index": N, "ts_ms": X}`, where `index` counts frames
+/// from zero and `ts_ms` is the number of milliseconds since the plugin was set up. Frames are produced at the given
+/// FPS, until the plugin signals that it is done or `frames` frames have been written.
+///
+/// # Arguments
+///
+/// * `options` - Options used to create the plugin
+/// * `fps` - Frames per second at which to pull frames from the plugin
+/// * `frames` - Maximum number of frames to write, or `None` to run until the plugin is done
+/// * `output` - Where to write the frames
+pub fn run_json_lines(
+    options: &PluginOptions,
+    fps: f32,
+    frames: Option<usize>,
+    mut output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let plugin_frames = HeadlessFrames::new(options)?.take(frames.unwrap_or(usize::MAX));
+
+    let time_at_start = Instant::now();
+    let time_between_frames = Duration::from_secs_f32(1.0 / fps);
+    let mut time_at_frame = Instant::now();
+    for (index, frame) in plugin_frames.enumerate() {
+        let line = json!({
+            "frame": frame?,
+            "index": index,
+            "ts_ms": time_at_start.elapsed().as_millis() as u64,
+        });
+        writeln!(output, "{line}")?;
+        output.flush()?;
+
+        // Wait until the next frame is due
+        if let Some(remaining) = time_between_frames.checked_sub(time_at_frame.elapsed()) {
+            sleep(remaining);
+        }
+        time_at_frame = Instant::now();
+    }
+
+    Ok(())
+}
+
+/// Run a plugin without a GUI, writing up to `count` frames to `output` as raw binary, returning how many were written
+///
+/// Each frame is written as its index, as a 4 byte little-endian integer, followed by its LEDs row by row, as 4 bytes
+/// each in BGRA order. Frames that don't fit the matrix are padded or trimmed, so every frame is the same size. Frames
+/// are pulled as fast as the plugin produces them.
+///
+/// # Arguments
+///
+/// * `options` - Options used to create the plugin
+/// * `channel_order` - Order of the color channels in each LED of the frames produced by the plugin
+/// * `count` - Maximum number of frames to write
+/// * `output` - Where to write the frames
+pub fn export_raw(
+    options: &PluginOptions,
+    channel_order: ChannelOrder,
+    count: usize,
+    mut output: impl Write,
+) -> Result<usize, Box<dyn Error>> {
+    let [red, green, blue, alpha] = channel_order;
+    let mut written = 0;
+    for (index, frame) in HeadlessFrames::new(options)?.take(count).enumerate() {
+        let mut frame = frame?;
+        if !options.matrix_config.fits(&frame) {
+            log::warn!("Frame {index} doesn't fit the matrix, so it was padded or trimmed.");
+            frame = options.matrix_config.fit_frame(frame);
+        }
+
+        output.write_all(&(index as u32).to_le_bytes())?;
+        for led in frame.iter().flatten() {
+            output.write_all(&[led[blue], led[green], led[red], led[alpha]])?;
+        }
+        written += 1;
+    }
+
+    output.flush()?;
+    Ok(written)
+}
+
+/// Run a plugin without a GUI, writing the frames it produces to `output` as a single JSON array, returning how many
+/// frames were written
+///
+/// Frames are pulled as fast as the plugin produces them, until the plugin signals that it is done or `frames` frames
+/// have been produced. Each frame is written as soon as it is produced, so the array is only complete once this
+/// returns successfully.
+///
+/// # Arguments
+///
+/// * `options` - Options used to create the plugin
+/// * `frames` - Maximum number of frames to write, or `None` to run until the plugin is done
+/// * `output` - Where to write the frames
+pub fn dump_frames(
+    options: &PluginOptions,
+    frames: Option<usize>,
+    mut output: impl Write,
+) -> Result<usize, Box<dyn Error>> {
+    let plugin_frames = HeadlessFrames::new(options)?.take(frames.unwrap_or(usize::MAX));
+
+    // Write the array a frame at a time, rather than holding every frame until the plugin is done
+    let mut written = 0;
+    output.write_all(b"[")?;
+    for frame in plugin_frames {
+        let frame = frame?;
+        if written > 0 {
+            output.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut output, &frame)?;
+        written += 1;
+    }
+    output.write_all(b"]")?;
+
+    output.flush()?;
+    Ok(written)
+}
+
+/// Describe an error from updating a plugin while the plugin is still alive
+///
+/// A trap inside the plugin refers back to the plugin, so it must not be formatted after the plugin is dropped.
+fn describe_update_error(e: UpdateError) -> Box<dyn Error> {
+    e.to_string().into()
+}
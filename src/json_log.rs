@@ -0,0 +1,96 @@
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use serde_json::json;
+use std::io::{stderr, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A logger that writes each log to stderr as a line of JSON, for log aggregators that expect structured output
+///
+/// Each line is a JSON object of the form `{"level": "INFO", "target": "simtricks", "msg": "...", "ts": X}`, where `ts`
+/// is the number of milliseconds since the Unix epoch.
+pub struct JsonLogger {
+    /// Level of each module to log, by the prefix of its target. Logs from any other module are dropped.
+    module_levels: Vec<(String, LevelFilter)>,
+}
+
+impl JsonLogger {
+    /// Create a logger that drops every log, until modules are added with `with_module_level`
+    pub fn new() -> Self {
+        Self {
+            module_levels: vec![],
+        }
+    }
+
+    /// Log the given module and its submodules at the given level
+    pub fn with_module_level(mut self, module: &str, level: LevelFilter) -> Self {
+        self.module_levels.push((module.to_string(), level));
+        self
+    }
+
+    /// Install this as the global logger
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max_level = self
+            .module_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or(LevelFilter::Off);
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    /// Get the level of the module that made a log with the given target
+    fn level_of(&self, target: &str) -> LevelFilter {
+        self.module_levels
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .next_back()
+            .unwrap_or(LevelFilter::Off)
+    }
+}
+
+impl Default for JsonLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_of(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_millis() as u64);
+
+        // Write the whole line at once, so lines from different threads don't interleave
+        let _ = writeln!(stderr().lock(), "{}", json_log_line(record, ts));
+    }
+
+    fn flush(&self) {
+        let _ = stderr().flush();
+    }
+}
+
+/// Format a log as a line of JSON, with the given number of milliseconds since the Unix epoch
+pub fn json_log_line(record: &Record, ts: u64) -> String {
+    json!({
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "msg": record.args().to_string(),
+        "ts": ts,
+    })
+    .to_string()
+}
@@ -0,0 +1,47 @@
+use crate::plugin::PluginSource;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Get the file that the plugins of the last launch are remembered in, if there is somewhere to keep it
+pub fn last_plugin_file() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("simtricks").join("last_plugin"))
+}
+
+/// Read the plugins remembered in the given file, as the paths or URLs they were given as
+///
+/// Returns an empty list if the file doesn't exist or can't be read.
+pub fn read_last_plugins(file: &Path) -> Vec<String> {
+    fs::read_to_string(file)
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Remember the given plugins in the given file, one per line, creating the file and its directory if needed
+///
+/// Paths are made absolute first, so the plugins can be found again from any directory. URLs are kept as they are.
+pub fn write_last_plugins(file: &Path, plugins: &[String]) -> Result<(), Box<dyn Error>> {
+    let current_dir = std::env::current_dir()?;
+    let lines: Vec<String> = plugins
+        .iter()
+        .map(|plugin| match PluginSource::parse(plugin) {
+            PluginSource::File(path) => current_dir.join(path).display().to_string(),
+            _ => plugin.clone(),
+        })
+        .collect();
+
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, lines.join("\n") + "\n")?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Plugin execution core of Simtricks, independent of the GUI.
+//!
+//! This library can be used to drive a Matricks plugin without opening a window, which is useful for
+//! testing plugins in CI.
+
+pub mod capture;
+pub mod color;
+pub mod display_transform;
+pub mod frame_dump;
+pub mod frame_server;
+pub mod headless;
+pub mod json_log;
+pub mod last_plugin;
+pub mod matrix_config;
+pub mod path_map;
+pub mod plugin;
+pub mod plugin_fps;
+pub mod plugin_frame;
+pub mod plugin_latency;
+pub mod plugin_logs;
+pub mod plugin_thread;
+pub mod raster;
+pub mod recording;
+pub mod test_pattern;
+
+/// A single frame of LED colors, indexed by row and then column
+pub type Frame = Vec<Vec<[u8; 4]>>;
+
+/// A frame shared between threads, which is replaced whole so that readers only ever hold the lock to copy a pointer
+pub type SharedFrame = std::sync::Arc<std::sync::Mutex<std::sync::Arc<Frame>>>;
@@ -1,15 +1,29 @@
 mod clargs;
-mod plugin_logs;
-mod plugin_thread;
+mod plugin_instance;
 mod simulator_app;
 
-use crate::simulator_app::Simulator;
-use clap::Parser;
+use crate::clargs::LogLevel;
+use crate::simulator_app::{PluginView, Simulator};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use eframe::egui::Visuals;
 use eframe::{egui, NativeOptions};
 use log::LevelFilter;
+use serde_json::json;
 use simple_logger::SimpleLogger;
-use std::path::PathBuf;
+use simtricks::color::load_lut;
+use simtricks::display_transform::DisplayTransform;
+use simtricks::headless::{dump_frames, export_raw, run_json_lines, save_single_frame};
+use simtricks::json_log::JsonLogger;
+use simtricks::last_plugin::{last_plugin_file, read_last_plugins, write_last_plugins};
+use simtricks::matrix_config::{MatrixConfiguration, RequestedDimensions};
+use simtricks::path_map::{remove_abandoned_copies, remove_staged_copies, PathMap};
+use simtricks::plugin::{create_plugin, preferred_dimensions, PluginOptions, PluginSource};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{stdin, stdout, BufWriter, IsTerminal, Read};
+use std::sync::{Arc, Mutex};
 
 const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 const DEFAULT_SIMTRICKS_LOG_LEVEL: LevelFilter = LevelFilter::Info;
@@ -21,13 +35,27 @@ fn main() {
     // Parse command line arguments
     let args = clargs::SimtricksArgs::parse();
 
-    // Start the logger
-    SimpleLogger::new()
-        .with_level(LevelFilter::Off)
-        .with_module_level("simtricks", DEFAULT_SIMTRICKS_LOG_LEVEL)
-        .with_module_level("matricks", DEFAULT_MATRICKS_LOG_LEVEL)
-        .init()
-        .expect("Unable to start logger!");
+    // Start the logger, at the level given by --verbose or --quiet if there is no explicit level
+    let log_level = args
+        .log_level
+        .or(args.verbose.then_some(LogLevel::Trace))
+        .or(args.quiet.then_some(LogLevel::Warn));
+    let simtricks_log_level = log_level.map_or(DEFAULT_SIMTRICKS_LOG_LEVEL, LevelFilter::from);
+    let matricks_log_level = log_level.map_or(DEFAULT_MATRICKS_LOG_LEVEL, LevelFilter::from);
+    if args.json_log {
+        JsonLogger::new()
+            .with_module_level("simtricks", simtricks_log_level)
+            .with_module_level("matricks", matricks_log_level)
+            .init()
+            .expect("Unable to start logger!");
+    } else {
+        SimpleLogger::new()
+            .with_level(LevelFilter::Off)
+            .with_module_level("simtricks", simtricks_log_level)
+            .with_module_level("matricks", matricks_log_level)
+            .init()
+            .expect("Unable to start logger!");
+    }
     log::info!("Starting Simtricks v{}", VERSION.unwrap_or("unknown"));
 
     // Setup window options
@@ -36,23 +64,257 @@ fn main() {
         ..Default::default()
     };
 
+    // Read the plugin piped to stdin, if requested
+    let stdin_source = if args.stdin {
+        match read_stdin() {
+            Ok(wasm_data) => Some(PluginSource::Stdin(Arc::new(wasm_data))),
+            Err(e) => {
+                log::error!("Failed to read plugin from stdin.");
+                log::error!("{e}");
+                log::info!("Exiting Simtricks.");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    // In the simulator window, remember the plugins given for next time, or open the plugins from last time if none
+    // were given
+    let headless = args.headless || args.once.is_some() || args.export_raw.is_some();
+    let windowed = !headless && !args.print_config;
+    let last_plugin_file = last_plugin_file().filter(|_| windowed);
+    let paths = if !args.path.is_empty() {
+        if let Some(file) = &last_plugin_file {
+            if let Err(e) = write_last_plugins(file, &args.path) {
+                log::warn!("Failed to remember the plugins for next time.");
+                log::debug!(
+                    "Writing {} failed with the following error: {e}",
+                    file.display()
+                );
+            }
+        }
+        args.path.clone()
+    } else if !windowed
+        || args.no_restore
+        || args.stdin
+        || args.test_pattern.is_some()
+        || args.play_frames.is_some()
+    {
+        vec![]
+    } else {
+        let paths = last_plugin_file
+            .as_deref()
+            .map(read_last_plugins)
+            .unwrap_or_default();
+        if !paths.is_empty() {
+            log::info!("Opening the plugins from last time: {}", paths.join(", "));
+        }
+        paths
+    };
+
     // Treat command line arguments
-    let path = PathBuf::from(args.path);
-    let dimensions = (args.width.clone(), args.height.clone());
+    let sources: Vec<PluginSource> = paths
+        .iter()
+        .map(|path| PluginSource::parse(path))
+        .chain(stdin_source)
+        .chain(args.test_pattern.map(PluginSource::TestPattern))
+        .chain(args.play_frames.clone().map(PluginSource::Frames))
+        .collect();
+    if sources.is_empty() {
+        clargs::SimtricksArgs::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "no plugin to open: pass --path, --stdin, --test-pattern, or --play-frames",
+            )
+            .exit();
+    }
+    let requested_dimensions = RequestedDimensions {
+        width: args.width,
+        height: args.height,
+    };
+    let (width, height) = requested_dimensions.or_default();
+    let mut matrix_config = MatrixConfiguration {
+        wiring: args.wiring,
+        ..MatrixConfiguration::new(width, height, args.fps)
+    };
+
+    // Warn about any plugin variable that the Matricks config takes the place of, once rather than for every plugin
+    let matricks_config = matrix_config.to_plugin_config();
+    for (key, _) in &args.var {
+        if matricks_config.contains_key(key) {
+            log::warn!("Ignoring plugin variable {key}, which is part of the Matricks config.");
+        }
+    }
     let allowed_hosts = args.allow_host.unwrap_or(vec![]);
-    let mapped_paths: Vec<(PathBuf, PathBuf)> = args
+    let mapped_paths: Vec<PathMap> = args
         .map_path
         .unwrap_or(vec![])
         .iter()
-        .map(|map_string| match map_string.split_once('>') {
-            None => (PathBuf::from(map_string), PathBuf::from(map_string.clone())),
-            Some(res) => (PathBuf::from(res.0), PathBuf::from(res.1)),
-        })
+        .map(|map_string| PathMap::parse(map_string))
         .collect();
 
+    // Clean up after any earlier run that crashed while it held copies of read-only paths
+    remove_abandoned_copies();
+
+    let display_transform = DisplayTransform {
+        rotation: args.rotate,
+        flip_h: args.flip_h,
+        flip_v: args.flip_v,
+        origin: args.origin,
+    };
+
+    // Without a window, take any missing width or height from the dimensions declared by the first plugin, as the
+    // simulator would
+    if (args.print_config || headless) && !requested_dimensions.is_complete() {
+        let options = PluginOptions {
+            allowed_hosts: allowed_hosts.clone(),
+            path_maps: mapped_paths.clone(),
+            vars: args.var.clone(),
+            ..PluginOptions::new(sources[0].clone(), matrix_config.clone())
+        };
+        if let Some(declared) = declared_dimensions(&options) {
+            (matrix_config.width, matrix_config.height) = requested_dimensions.fill(declared);
+        }
+    }
+
+    // Print the resolved configuration and exit, if requested
+    if args.print_config {
+        // Print the config exactly as it is handed to the plugin, and paths that aren't UTF-8 as near as possible
+        let config = json!({
+            "config": matrix_config.to_plugin_config(),
+            "allowed_hosts": allowed_hosts,
+            "path_maps": mapped_paths
+                .iter()
+                .map(|map| json!({
+                    "local": map.local.to_string_lossy(),
+                    "plugin": map.plugin.to_string_lossy(),
+                    "read_only": map.read_only,
+                }))
+                .collect::<Vec<_>>(),
+            "vars": args.var.iter().cloned().collect::<BTreeMap<_, _>>(),
+        });
+        println!("{config:#}");
+        return;
+    }
+
+    // Open the file to append plugin logs to, carrying on without it if it can't be opened
+    let plugin_log_file = args.plugin_log_file.as_ref().and_then(|path| {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(Arc::new(Mutex::new(BufWriter::new(file)))),
+            Err(e) => {
+                log::warn!(
+                    "Failed to open plugin log file {}. Plugin logs will not be saved.",
+                    path.display()
+                );
+                log::debug!("Opening the plugin log file failed with the following error: {e}");
+                None
+            }
+        }
+    });
+
+    // Run without a window, if requested
+    if headless {
+        if sources.len() > 1 {
+            log::error!("Only one plugin can be run headless.");
+            log::info!("Exiting Simtricks.");
+            return;
+        }
+        let options = PluginOptions {
+            allowed_hosts,
+            path_maps: mapped_paths,
+            vars: args.var,
+            log_file: plugin_log_file,
+            ..PluginOptions::new(sources[0].clone(), matrix_config)
+        };
+        let result = match (&args.once, &args.export_raw) {
+            (Some(path), _) => save_single_frame(&options, args.channel_order, path)
+                .inspect(|_| log::info!("Saved frame to {}.", path.display())),
+            (None, Some(path)) => File::create(path)
+                .map_err(Into::into)
+                .and_then(|file| {
+                    export_raw(
+                        &options,
+                        args.channel_order,
+                        args.export_count.unwrap_or_default(),
+                        BufWriter::new(file),
+                    )
+                })
+                .map(|frames| log::info!("Exported {frames} frames to {}.", path.display())),
+            (None, None) => match &args.dump_frames {
+                Some(path) => File::create(path)
+                    .map_err(Into::into)
+                    .and_then(|file| dump_frames(&options, args.frames, BufWriter::new(file)))
+                    .map(|frames| log::info!("Dumped {frames} frames to {}.", path.display())),
+                None => run_json_lines(&options, args.fps, args.frames, stdout().lock()),
+            },
+        };
+
+        // Exit with a failure status if the plugin crashed, so scripts can tell it apart from a plugin that finished
+        remove_staged_copies();
+        if let Err(e) = result {
+            log::error!("Failed to run plugin headless.");
+            log::error!("{e}");
+            log::info!("Exiting Simtricks.");
+            std::process::exit(1);
+        }
+        log::info!("Exiting Simtricks.");
+        return;
+    }
+
+    // Load the color lookup table, showing colors unchanged if it can't be loaded
+    let lut = args.lut.as_ref().and_then(|path| match load_lut(path) {
+        Ok(lut) => Some(lut),
+        Err(e) => {
+            log::warn!(
+                "Failed to load color lookup table from {}. Showing colors unchanged.",
+                path.display()
+            );
+            log::debug!("Loading the color lookup table failed with the following error: {e}");
+            None
+        }
+    });
+
     // Create the simulator
-    let simulator = match Simulator::new(path, dimensions, args.fps, allowed_hosts, mapped_paths) {
-        Ok(sim) => sim,
+    let mut simulator = match Simulator::new(
+        sources,
+        matrix_config,
+        allowed_hosts,
+        mapped_paths,
+        args.history_size,
+        args.warmup,
+    ) {
+        Ok(sim) => sim
+            .with_display_transform(display_transform)
+            .with_led_aspect(args.led_aspect)
+            .with_channel_order(args.channel_order)
+            .with_alpha_mode(args.alpha_mode)
+            .with_background(args.background)
+            .with_lut(lut)
+            .with_plugin_log_file(plugin_log_file)
+            .with_plugin_vars(args.var)
+            .with_restart_confirmation(!args.no_restart_confirmation)
+            .with_clear_on_restart(!args.keep_frame_on_restart)
+            .with_requested_dimensions(requested_dimensions)
+            .with_strict_setup(args.strict_setup)
+            .with_strict_dimensions(args.strict_dimensions)
+            .with_pause_on_error(args.pause_on_error)
+            .with_view(if args.tabs {
+                PluginView::Tabs
+            } else if args.diff {
+                PluginView::Diff
+            } else {
+                PluginView::SideBySide
+            })
+            .with_diff_threshold(args.diff_threshold)
+            .with_diff_overlay(args.diff_overlay)
+            .with_smoothing(args.smooth)
+            .with_plugin_fps(args.respect_plugin_fps)
+            .with_continuous_repaint(args.continuous_repaint)
+            .with_dirty_tracking(!args.no_dirty_tracking)
+            .with_snapshot_key(args.snapshot_key)
+            .with_capture(args.capture_every, args.capture_dir)
+            .with_launch_display_settings(),
         Err(e) => {
             log::error!("Failed to create simulator.");
             log::debug!("Recieved the following error while creating the simulator: {e}");
@@ -61,6 +323,14 @@ fn main() {
         }
     };
 
+    // Stream frames to WebSocket clients, if requested
+    if let Some(ws_port) = args.ws_port {
+        if let Err(e) = simulator.stream_frames(ws_port) {
+            log::error!("Failed to start WebSocket server.");
+            log::error!("{e}");
+        }
+    }
+
     // Start the simulator
     match eframe::run_native(
         "Simtricks",
@@ -78,4 +348,32 @@ fn main() {
             log::info!("Exiting Simtricks.");
         }
     };
+    remove_staged_copies();
+}
+
+/// Read the whole of stdin, refusing to wait for a plugin to be typed in if nothing is piped
+fn read_stdin() -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut stdin = stdin().lock();
+    if stdin.is_terminal() {
+        return Err("Nothing was piped to stdin.".into());
+    }
+
+    let mut wasm_data = Vec::new();
+    stdin.read_to_end(&mut wasm_data)?;
+    if wasm_data.is_empty() {
+        return Err("Stdin was empty.".into());
+    }
+    Ok(wasm_data)
+}
+
+/// Load the plugin to read the dimensions it declares, if any
+fn declared_dimensions(options: &PluginOptions) -> Option<(usize, usize)> {
+    match create_plugin(options) {
+        Ok(mut plugin) => preferred_dimensions(&mut plugin),
+        Err(e) => {
+            log::warn!("Failed to load plugin to read its dimensions.");
+            log::debug!("Recieved the following error while loading the plugin: {e}");
+            None
+        }
+    }
 }
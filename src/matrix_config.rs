@@ -0,0 +1,163 @@
+use crate::Frame;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// How the LEDs of the matrix are wired together
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Wiring {
+    /// Along each row, reversing direction at the end of every row
+    #[default]
+    RowSerpentine,
+
+    /// Down each column, reversing direction at the end of every column
+    ColSerpentine,
+
+    /// Along each row, always in the same direction
+    RowProgressive,
+
+    /// Down each column, always in the same direction
+    ColProgressive,
+}
+
+impl Wiring {
+    /// Returns true if the wiring reverses direction at the end of every row or column
+    pub fn is_serpentine(&self) -> bool {
+        matches!(self, Wiring::RowSerpentine | Wiring::ColSerpentine)
+    }
+
+    /// Get the axis that the wiring runs along, either "rows" or "columns"
+    pub fn axis(&self) -> &'static str {
+        match self {
+            Wiring::RowSerpentine | Wiring::RowProgressive => "rows",
+            Wiring::ColSerpentine | Wiring::ColProgressive => "columns",
+        }
+    }
+}
+
+impl Display for Wiring {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = self.to_possible_value().expect("no wiring is skipped");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// Width of the matrix used when neither the command line nor the plugin gives one
+pub const DEFAULT_MATRIX_WIDTH: usize = 16;
+
+/// Height of the matrix used when neither the command line nor the plugin gives one
+pub const DEFAULT_MATRIX_HEIGHT: usize = 16;
+
+/// Dimensions of the matrix given on the command line, where a missing width or height is left to the plugin
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestedDimensions {
+    /// Width of the matrix, if given
+    pub width: Option<usize>,
+
+    /// Height of the matrix, if given
+    pub height: Option<usize>,
+}
+
+impl RequestedDimensions {
+    /// Check whether both the width and height were given, leaving nothing for the plugin to choose
+    pub fn is_complete(&self) -> bool {
+        self.width.is_some() && self.height.is_some()
+    }
+
+    /// Get the dimensions (width, then height), taking any missing width or height from the declared dimensions
+    pub fn fill(&self, declared: (usize, usize)) -> (usize, usize) {
+        (
+            self.width.unwrap_or(declared.0),
+            self.height.unwrap_or(declared.1),
+        )
+    }
+
+    /// Get the dimensions (width, then height), using the default for any missing width or height
+    pub fn or_default(&self) -> (usize, usize) {
+        self.fill((DEFAULT_MATRIX_WIDTH, DEFAULT_MATRIX_HEIGHT))
+    }
+}
+
+/// Configuration of the matrix, as passed to a Matricks plugin
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatrixConfiguration {
+    /// Width of the matrix, in number of LEDs
+    pub width: usize,
+
+    /// Height of the matrix, in number of LEDs
+    pub height: usize,
+
+    /// Frames per second that the plugin should target
+    pub target_fps: f32,
+
+    /// How the LEDs of the matrix are wired together
+    pub wiring: Wiring,
+
+    /// Brightness of the matrix, from 0 to 255
+    pub brightness: u8,
+}
+
+impl MatrixConfiguration {
+    /// Create a configuration for a matrix wired in a row serpentine pattern, at full brightness
+    pub fn new(width: usize, height: usize, target_fps: f32) -> Self {
+        Self {
+            width,
+            height,
+            target_fps,
+            wiring: Wiring::default(),
+            brightness: 255,
+        }
+    }
+
+    /// Get the dimensions of the matrix (width, then height)
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Create the config map that is handed to the plugin
+    ///
+    /// Matricks only knows whether the matrix is serpentine, so the full wiring is also passed through as the custom
+    /// `wiring` and `wiring_axis` keys.
+    pub fn to_plugin_config(&self) -> BTreeMap<String, Option<String>> {
+        let mut matricks_config: BTreeMap<String, Option<String>> = BTreeMap::new();
+        matricks_config.insert(String::from("width"), Some(format!("{}", self.width)));
+        matricks_config.insert(String::from("height"), Some(format!("{}", self.height)));
+        matricks_config.insert(
+            String::from("target_fps"),
+            Some(format!("{}", self.target_fps)),
+        );
+        matricks_config.insert(
+            String::from("serpentine"),
+            Some(format!("{}", self.wiring.is_serpentine())),
+        );
+        matricks_config.insert(String::from("wiring"), Some(self.wiring.to_string()));
+        matricks_config.insert(
+            String::from("wiring_axis"),
+            Some(String::from(self.wiring.axis())),
+        );
+        matricks_config.insert(
+            String::from("brightness"),
+            Some(format!("{}", self.brightness)),
+        );
+        matricks_config
+    }
+
+    /// Create a blank frame with the dimensions of this matrix
+    pub fn blank_frame(&self) -> Vec<Vec<[u8; 4]>> {
+        vec![vec![[0; 4]; self.width]; self.height]
+    }
+
+    /// Returns true if the frame has the dimensions of this matrix
+    pub fn fits(&self, frame: &[Vec<[u8; 4]>]) -> bool {
+        frame.len() == self.height && frame.iter().all(|row| row.len() == self.width)
+    }
+
+    /// Pad or trim a frame to the dimensions of this matrix, filling any missing LEDs with blank LEDs
+    pub fn fit_frame(&self, mut frame: Frame) -> Frame {
+        frame.resize(self.height, vec![]);
+        for row in &mut frame {
+            row.resize(self.width, [0; 4]);
+        }
+        frame
+    }
+}
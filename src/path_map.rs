@@ -0,0 +1,171 @@
+//! Paths on the local filesystem mapped into the plugin filesystem.
+//!
+//! Extism gives a plugin read and write access to every mapped path, so a read-only map is given to the plugin as a copy
+//! of the local path, made the first time it is needed. The plugin can still write to the copy, which keeps its changes
+//! until Simtricks exits, even when the plugin is restarted, but the original is left alone. Symbolic links inside a
+//! copied directory are left out of the copy.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Suffix marking a path map as read-only
+const READ_ONLY_SUFFIX: &str = ":ro";
+
+/// Suffix marking a path map as read-write, which is the default
+const READ_WRITE_SUFFIX: &str = ":rw";
+
+/// Copies of read-only paths made by this process, by local path, so that each path is only copied once
+static STAGED_COPIES: Mutex<BTreeMap<PathBuf, PathBuf>> = Mutex::new(BTreeMap::new());
+
+/// A path on the local filesystem, mapped to a path on the plugin filesystem
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathMap {
+    /// Path on the local filesystem
+    pub local: PathBuf,
+
+    /// Where the plugin sees the local path
+    pub plugin: PathBuf,
+
+    /// If true, the plugin is given a copy of the local path, so that it can't change the original, though it can still
+    /// write to the copy
+    pub read_only: bool,
+}
+
+impl PathMap {
+    /// Parse a path map of the form "LOCAL_PATH>PLUGIN_PATH", optionally followed by ":ro" or ":rw"
+    ///
+    /// A single path is mapped to the same path on the plugin filesystem. Without a suffix, the map is read-write.
+    pub fn parse(map: &str) -> PathMap {
+        // Strip the access suffix, if there is one
+        let (map, read_only) = if let Some(map) = map.strip_suffix(READ_ONLY_SUFFIX) {
+            (map, true)
+        } else {
+            (map.strip_suffix(READ_WRITE_SUFFIX).unwrap_or(map), false)
+        };
+
+        let (local, plugin) = map.split_once('>').unwrap_or((map, map));
+        PathMap {
+            local: PathBuf::from(local),
+            plugin: PathBuf::from(plugin),
+            read_only,
+        }
+    }
+
+    /// Get the local path to give the plugin, which is a copy of the local path if the map is read-only
+    ///
+    /// The copy is made the first time it is needed, and then given to every plugin that maps the same path until
+    /// Simtricks exits, so anything a plugin writes to it is seen by the plugins that follow, including the same plugin
+    /// once it is restarted.
+    pub fn stage(&self) -> io::Result<PathBuf> {
+        if !self.read_only {
+            return Ok(self.local.clone());
+        }
+
+        // Reuse the copy made for an earlier plugin, if there is one
+        let mut copies = STAGED_COPIES.lock().unwrap();
+        if let Some(copy) = copies.get(&self.local) {
+            return Ok(copy.clone());
+        }
+
+        let copy = staging_dir().join(copies.len().to_string());
+        fs::create_dir_all(staging_dir())?;
+        copy_path(&self.local, &copy)?;
+        copies.insert(self.local.clone(), copy.clone());
+        Ok(copy)
+    }
+}
+
+/// Get the directory holding the copies of read-only paths made by every run of Simtricks, each in a directory named
+/// after its process ID
+fn staging_root() -> PathBuf {
+    std::env::temp_dir().join("simtricks-read-only")
+}
+
+/// Get the directory holding the copies of read-only paths made by this process
+pub fn staging_dir() -> PathBuf {
+    staging_root().join(std::process::id().to_string())
+}
+
+/// Remove the copies of read-only paths made by this process
+pub fn remove_staged_copies() {
+    STAGED_COPIES.lock().unwrap().clear();
+    match fs::remove_dir_all(staging_dir()) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            log::warn!("Failed to remove copies of read-only paths.");
+            log::debug!(
+                "Removing {} failed with the following error: {e}",
+                staging_dir().display()
+            );
+        }
+    }
+}
+
+/// Remove the copies of read-only paths left behind by earlier runs of Simtricks that are no longer running, i.e. that
+/// crashed before they could remove their own
+pub fn remove_abandoned_copies() {
+    let Ok(entries) = fs::read_dir(staging_root()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let pid = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok());
+        if pid.map_or(true, |pid| pid == std::process::id() || is_running(pid)) {
+            continue;
+        }
+
+        // Copies made by other users can't be removed, so don't warn about them
+        if let Err(e) = fs::remove_dir_all(entry.path()) {
+            log::debug!(
+                "Removing {} failed with the following error: {e}",
+                entry.path().display()
+            );
+        }
+    }
+}
+
+/// Check whether a process is still running, assuming that it is where that can't be checked
+fn is_running(pid: u32) -> bool {
+    if !cfg!(unix) {
+        return true;
+    }
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .map_or(true, |status| status.success())
+}
+
+/// Copy a file, or a directory and everything in it
+///
+/// Symbolic links inside the directory are skipped rather than followed, so that a link back up the directory can't
+/// make the copy go on forever.
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let destination = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            log::warn!(
+                "Leaving the symbolic link {} out of the read-only copy.",
+                entry.path().display()
+            );
+        } else if file_type.is_dir() {
+            copy_path(&entry.path(), &destination)?;
+        } else {
+            fs::copy(entry.path(), destination)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,461 @@
+use crate::matrix_config::MatrixConfiguration;
+use crate::path_map::PathMap;
+use crate::plugin_fps::{plugin_set_target_fps, RequestedFps};
+use crate::plugin_frame::parse_frame;
+use crate::plugin_logs;
+use crate::plugin_logs::{LogBreakpoint, PluginLogBuffer, PluginLogFile, PluginLogSinks};
+use crate::test_pattern::TestPattern;
+use extism::manifest::Wasm;
+use extism::{Function, Manifest, Plugin, ValType};
+use log::Level;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{create_dir_all, read, write};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::{from_utf8, Utf8Error};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Longest to wait for a plugin download to connect, or to send more of the plugin, before giving up on it
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest plugin that will be downloaded, in bytes
+const MAX_DOWNLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Where to load a plugin from
+#[derive(Clone, Debug, PartialEq)]
+pub enum PluginSource {
+    /// A WASM file on the local filesystem
+    File(PathBuf),
+
+    /// A WASM file hosted online, at an HTTP(S) URL
+    Url(String),
+
+    /// A WASM file piped to the simulator on stdin, read in full before the simulator starts
+    Stdin(Arc<Vec<u8>>),
+
+    /// A built-in test pattern, shown in place of a plugin
+    TestPattern(TestPattern),
+
+    /// Frames dumped from a plugin as a JSON file, replayed in place of the plugin
+    Frames(PathBuf),
+}
+
+impl PluginSource {
+    /// Interpret a string as either a URL (if it starts with `http://` or `https://`) or a path
+    pub fn parse(source: &str) -> Self {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            PluginSource::Url(source.to_string())
+        } else {
+            PluginSource::File(PathBuf::from(source))
+        }
+    }
+
+    /// Get the name of the plugin file, without the directories or the rest of the URL leading to it
+    pub fn file_name(&self) -> String {
+        match self {
+            PluginSource::File(path) | PluginSource::Frames(path) => path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            PluginSource::Url(url) => {
+                let path = url.split(['?', '#']).next().unwrap_or(url);
+                match path.trim_end_matches('/').rsplit_once('/') {
+                    Some((_, name)) if !name.is_empty() => name.to_string(),
+                    _ => url.clone(),
+                }
+            }
+            PluginSource::Stdin(_) => String::from("stdin"),
+            PluginSource::TestPattern(pattern) => pattern.name().to_string(),
+        }
+    }
+
+    /// Load the WASM data of the plugin
+    ///
+    /// Plugins downloaded from a URL are cached, so loading the same URL again does not download it again. A download
+    /// fails if the server stops responding for 30 seconds, or if the plugin is larger than 64 MB.
+    pub fn load(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            PluginSource::File(path) => Ok(read(path)?),
+            PluginSource::Url(url) => {
+                static DOWNLOADS: OnceLock<Mutex<BTreeMap<String, Vec<u8>>>> = OnceLock::new();
+                let downloads = DOWNLOADS.get_or_init(|| Mutex::new(BTreeMap::new()));
+
+                if let Some(wasm_data) = downloads.lock().unwrap().get(url) {
+                    log::debug!("Using cached download of plugin from {url}.");
+                    return Ok(wasm_data.clone());
+                }
+
+                // Download without holding the lock, so a slow download doesn't hold up loading any other plugin
+                log::info!("Downloading plugin from {url}.");
+                let wasm_data = download(url)
+                    .map_err(|e| format!("Failed to download plugin from {url}: {e}"))?;
+                downloads
+                    .lock()
+                    .unwrap()
+                    .insert(url.clone(), wasm_data.clone());
+                Ok(wasm_data)
+            }
+            PluginSource::Stdin(wasm_data) => Ok(wasm_data.as_ref().clone()),
+            PluginSource::TestPattern(pattern) => Err(format!(
+                "The {} test pattern is built in, and can only be shown in the simulator window.",
+                pattern.name().to_lowercase()
+            )
+            .into()),
+            PluginSource::Frames(path) => Err(format!(
+                "{} holds dumped frames rather than a plugin, and can only be played in the simulator window.",
+                path.display()
+            )
+            .into()),
+        }
+    }
+}
+
+impl Display for PluginSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginSource::File(path) => write!(f, "{}", path.display()),
+            PluginSource::Url(url) => write!(f, "{url}"),
+            PluginSource::Stdin(_) => write!(f, "plugin read from stdin"),
+            PluginSource::TestPattern(pattern) => {
+                write!(f, "{} test pattern", pattern.name())
+            }
+            PluginSource::Frames(path) => write!(f, "frames from {}", path.display()),
+        }
+    }
+}
+
+/// Download the body of the given URL, giving up if it is too slow or too large
+fn download(url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(DOWNLOAD_TIMEOUT)
+        .timeout_read(DOWNLOAD_TIMEOUT)
+        .build();
+
+    // Read one byte more than allowed, to tell a plugin of exactly the largest size from one that is too large
+    let mut wasm_data = Vec::new();
+    agent
+        .get(url)
+        .call()?
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut wasm_data)?;
+    if wasm_data.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(format!(
+            "plugin is larger than {} MB",
+            MAX_DOWNLOAD_BYTES / (1024 * 1024)
+        )
+        .into());
+    }
+    Ok(wasm_data)
+}
+
+/// Everything needed to create a new instance of a Matricks plugin
+pub struct PluginOptions {
+    /// Where to load the plugin from
+    pub source: PluginSource,
+
+    /// Configuration of the matrix, passed to the plugin
+    pub matrix_config: MatrixConfiguration,
+
+    /// Network hosts that the plugin may communicate with
+    pub allowed_hosts: Vec<String>,
+
+    /// Map a location on the host filesystem to the plugin filesystem
+    pub path_maps: Vec<PathMap>,
+
+    /// Extra variables for the plugin, as keys and values, put in the manifest config alongside the Matricks config
+    pub vars: Vec<(String, String)>,
+
+    /// Shared simulator state to act on when the plugin makes a warn or error log, if any
+    pub log_breakpoint: Option<LogBreakpoint>,
+
+    /// Where to keep the most recent logs made by the plugin, if anywhere
+    pub log_buffer: Option<PluginLogBuffer>,
+
+    /// File to append each log made by the plugin to, if any
+    pub log_file: Option<PluginLogFile>,
+
+    /// Where to store the frame rate requested by the plugin through `matricks_set_target_fps`, if anywhere
+    pub requested_fps: Option<RequestedFps>,
+}
+
+impl PluginOptions {
+    /// Options for loading a plugin from the given source onto the given matrix, with no hosts, paths, variables, or log
+    /// sinks
+    pub fn new(source: PluginSource, matrix_config: MatrixConfiguration) -> Self {
+        PluginOptions {
+            source,
+            matrix_config,
+            allowed_hosts: vec![],
+            path_maps: vec![],
+            vars: vec![],
+            log_breakpoint: None,
+            log_buffer: None,
+            log_file: None,
+            requested_fps: None,
+        }
+    }
+}
+
+/// Create a new plugin from the given options
+pub fn create_plugin(options: &PluginOptions) -> Result<Plugin<'static>, Box<dyn Error>> {
+    // Pull WASM data from the given source
+    let wasm_data = options.source.load()?;
+    let wasm = Wasm::from(wasm_data);
+
+    // Give the plugin its mapped paths, with a copy of each read-only one so the original can't be changed
+    let allowed_paths = options
+        .path_maps
+        .iter()
+        .map(|map| Ok((map.stage()?, map.plugin.clone())))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+
+    // Create a new manifest for the plugin
+    let manifest = Manifest::new([wasm])
+        .with_allowed_hosts(options.allowed_hosts.clone().into_iter())
+        .with_allowed_paths(allowed_paths.into_iter())
+        .with_config(options.vars.clone().into_iter());
+
+    // Create the config, which takes the place of any variable with the same key
+    let matricks_config = options.matrix_config.to_plugin_config();
+
+    // Setup the host functions. The log functions hold the log breakpoint, buffer, and file themselves, rather than as
+    // user data, which they can't reliably read back.
+    let log_sinks = PluginLogSinks {
+        breakpoint: options.log_breakpoint.clone(),
+        buffer: options.log_buffer.clone(),
+        file: options.log_file.clone(),
+    };
+    let plugin_log_function = |name: &str, level: Level| {
+        let log_sinks = log_sinks.clone();
+        Function::new(
+            name,
+            [ValType::I64],
+            [],
+            None,
+            move |plugin, inputs, _outputs, _user_data| {
+                plugin_logs::plugin_log(plugin, inputs, level, &log_sinks)
+            },
+        )
+    };
+    let plugin_debug_log_function = plugin_log_function("matricks_debug", Level::Debug);
+    let plugin_info_log_function = plugin_log_function("matricks_info", Level::Info);
+    let plugin_warn_log_function = plugin_log_function("matricks_warn", Level::Warn);
+    let plugin_error_log_function = plugin_log_function("matricks_error", Level::Error);
+    let requested_fps = options.requested_fps.clone();
+    let plugin_set_target_fps_function = Function::new(
+        "matricks_set_target_fps",
+        [ValType::F32],
+        [],
+        None,
+        move |_plugin, inputs, _outputs, _user_data| {
+            plugin_set_target_fps(inputs, requested_fps.as_ref())
+        },
+    );
+    let plugin_functions = [
+        plugin_debug_log_function,
+        plugin_info_log_function,
+        plugin_warn_log_function,
+        plugin_error_log_function,
+        plugin_set_target_fps_function,
+    ];
+
+    // Create the plugin
+    let plugin = Plugin::create_with_manifest(&manifest, plugin_functions, true)?
+        .with_config(&matricks_config)?;
+
+    Ok(plugin)
+}
+
+/// Call the setup function of the plugin, logging the result
+pub fn setup_plugin(plugin: &mut Plugin) -> Result<(), extism::Error> {
+    match plugin.call("setup", "") {
+        Ok(_) => {
+            log::info!("Successfully set up plugin.");
+            Ok(())
+        }
+        Err(e) => {
+            log::warn!("Failed to set up plugin.");
+            log::debug!("Failed to set up plugin with following error: {e}");
+            Err(e)
+        }
+    }
+}
+
+/// An error encountered while pulling a new frame from a plugin
+#[derive(Debug)]
+pub enum UpdateError {
+    /// The call to the plugin's update function failed
+    Call(extism::Error),
+
+    /// The update was not valid UTF8
+    Utf8(Utf8Error),
+
+    /// The update could not be deserialized into a frame
+    Deserialize(serde_json::Error),
+}
+
+impl Display for UpdateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Call(e) => write!(f, "Failed to receive update from plugin: {e}"),
+            UpdateError::Utf8(e) => write!(f, "Failed to convert update from UTF8: {e}"),
+            UpdateError::Deserialize(e) => write!(f, "Invalid update returned from plugin: {e}"),
+        }
+    }
+}
+
+impl Error for UpdateError {}
+
+/// Find the host that a plugin was refused, from the error of a call to the plugin
+///
+/// Extism fails the whole call when a plugin makes an HTTP request to a host that isn't allowed, so the refused host is
+/// only known from the error message. Returns `None` if the error wasn't caused by a refused request.
+pub fn denied_host(error: &str) -> Option<&str> {
+    // Pull the URL out of the message, of the form "HTTP request to {url} is not allowed". This is the wording of
+    // extism-runtime 0.5.5 (src/pdk.rs), so check it still matches whenever Extism is upgraded.
+    let (_, url) = error.split_once("HTTP request to ")?;
+    let (url, _) = url.split_once(" is not allowed")?;
+
+    // Strip the scheme, path, user, and port from the URL, leaving the host
+    let authority = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = authority.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+/// Explain how to let a plugin reach the host it was refused, if the error of a call to the plugin was caused by one
+pub fn sandbox_hint(error: &str) -> Option<String> {
+    denied_host(error).map(|host| {
+        format!("The plugin tried to reach {host}, which is not an allowed host. Pass --allow-host {host} to allow it.")
+    })
+}
+
+/// Pull the next frame from the plugin
+///
+/// Returns `None` if the plugin has signalled that it will not provide any more updates.
+pub fn next_frame(plugin: &mut Plugin) -> Result<Option<Vec<Vec<[u8; 4]>>>, UpdateError> {
+    // Attempt to pull the next frame from the plugin, as a UTF8 JSON string
+    let new_state_utf8 = plugin.call("update", "").map_err(UpdateError::Call)?;
+
+    // Convert the UTF8 to a string
+    let new_state_str = from_utf8(new_state_utf8).map_err(UpdateError::Utf8)?;
+
+    // Deserialize the new state from a string, accepting LEDs with three or four channels
+    parse_frame(new_state_str).map_err(UpdateError::Deserialize)
+}
+
+/// Ask the plugin for the dimensions of the matrix it was designed for (width, then height)
+///
+/// Plugins may declare their dimensions with an optional `dimensions` export, returning JSON of the form
+/// `{"width": W, "height": H}`. Returns `None` if the export is missing, fails, or returns anything else.
+pub fn preferred_dimensions(plugin: &mut Plugin) -> Option<(usize, usize)> {
+    let dimensions_str = call_string_export(plugin, "dimensions")?;
+
+    // Pull the width and height out of the JSON, which must both be at least one
+    let dimensions = serde_json::from_str::<serde_json::Value>(&dimensions_str)
+        .ok()
+        .and_then(|dimensions| {
+            let dimension = |key: &str| {
+                dimensions
+                    .get(key)?
+                    .as_u64()
+                    .filter(|dimension| *dimension > 0)
+                    .map(|dimension| dimension as usize)
+            };
+            Some((dimension("width")?, dimension("height")?))
+        });
+
+    if dimensions.is_none() {
+        log::warn!("Plugin declared invalid dimensions.");
+        log::debug!("Plugin returned the following from its dimensions export: {dimensions_str}");
+    }
+    dimensions
+}
+
+/// Save the internal state of the plugin to a file, using its optional `serialize_state` export
+pub fn save_state(plugin: &mut Plugin, path: &Path) -> Result<(), Box<dyn Error>> {
+    if !plugin.has_function("serialize_state") {
+        return Err("Plugin does not export serialize_state.".into());
+    }
+    let state = plugin.call("serialize_state", "")?.to_vec();
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    write(path, state)?;
+    Ok(())
+}
+
+/// Restore the internal state of the plugin from a file, using its optional `deserialize_state` export
+pub fn load_state(plugin: &mut Plugin, path: &Path) -> Result<(), Box<dyn Error>> {
+    if !plugin.has_function("deserialize_state") {
+        return Err("Plugin does not export deserialize_state.".into());
+    }
+    let state = read(path)?;
+    plugin.call("deserialize_state", state)?;
+    Ok(())
+}
+
+/// Metadata that a plugin may provide through custom exports
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PluginMetadata {
+    /// Name of the plugin, from the `plugin_name` export, or the `name` export if there is no `plugin_name`
+    pub name: Option<String>,
+
+    /// Version of the plugin, from the `plugin_version` export
+    pub version: Option<String>,
+
+    /// Author of the plugin, from the `plugin_author` export
+    pub author: Option<String>,
+}
+
+impl PluginMetadata {
+    /// Read any metadata the plugin exports, ignoring exports that are missing or fail
+    pub fn read(plugin: &mut Plugin) -> Self {
+        Self {
+            name: call_string_export(plugin, "plugin_name")
+                .or_else(|| call_string_export(plugin, "name")),
+            version: call_string_export(plugin, "plugin_version"),
+            author: call_string_export(plugin, "plugin_author"),
+        }
+    }
+
+    /// Describe the plugin in a single line (i.e. "name v1.0 by author"), if it provided a name
+    pub fn describe(&self) -> Option<String> {
+        let mut description = self.name.clone()?;
+        if let Some(version) = &self.version {
+            description.push_str(&format!(" v{version}"));
+        }
+        if let Some(author) = &self.author {
+            description.push_str(&format!(" by {author}"));
+        }
+        Some(description)
+    }
+}
+
+/// Call an export of the plugin which takes no input and returns a string
+///
+/// Returns `None` if the export doesn't exist, fails, or returns something other than a non-empty UTF8 string.
+fn call_string_export(plugin: &mut Plugin, name: &str) -> Option<String> {
+    if !plugin.has_function(name) {
+        return None;
+    }
+
+    match plugin.call(name, "") {
+        Ok(utf8) => match from_utf8(utf8) {
+            Ok(str) if !str.trim().is_empty() => Some(str.trim().to_string()),
+            _ => None,
+        },
+        Err(e) => {
+            log::debug!("Failed to call plugin export '{name}' with the following error: {e}");
+            None
+        }
+    }
+}
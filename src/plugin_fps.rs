@@ -0,0 +1,28 @@
+use extism::Val;
+use std::sync::{Arc, Mutex};
+
+/// Frame rate requested by a plugin through `matricks_set_target_fps`, if it has requested one
+pub type RequestedFps = Arc<Mutex<Option<f32>>>;
+
+/// Returns true if the frame rate can be run at, i.e. it is finite and positive
+pub fn is_valid_fps(fps: f32) -> bool {
+    fps.is_finite() && fps > 0.0
+}
+
+/// Record the frame rate that a plugin would like to run at, if there is somewhere to record it
+pub(crate) fn plugin_set_target_fps(
+    inputs: &[Val],
+    requested_fps: Option<&RequestedFps>,
+) -> Result<(), extism::Error> {
+    let fps = inputs[0].f32().unwrap();
+    if !is_valid_fps(fps) {
+        log::warn!("Ignoring invalid target FPS of {fps} requested by plugin.");
+        return Ok(());
+    }
+
+    log::info!("Plugin requested a target FPS of {fps}.");
+    if let Some(requested_fps) = requested_fps {
+        *requested_fps.lock().unwrap() = Some(fps);
+    }
+    Ok(())
+}
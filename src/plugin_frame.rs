@@ -0,0 +1,57 @@
+use crate::Frame;
+use serde::de::{Error, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt::Formatter;
+
+/// A single LED as returned by a plugin, with either three or four channels
+///
+/// Three channel LEDs are given an opaque fourth channel, as the last channel of the default channel order is alpha.
+struct PluginLed([u8; 4]);
+
+impl<'de> Deserialize<'de> for PluginLed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(PluginLedVisitor)
+    }
+}
+
+/// Visitor reading an LED from an array of three or four channels
+struct PluginLedVisitor;
+
+impl<'de> Visitor<'de> for PluginLedVisitor {
+    type Value = PluginLed;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("an array of 3 or 4 color channels")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PluginLed, A::Error> {
+        let mut led = [0, 0, 0, u8::MAX];
+        for (index, channel) in led.iter_mut().enumerate() {
+            match seq.next_element()? {
+                Some(value) => *channel = value,
+                None if index == 3 => return Ok(PluginLed(led)),
+                None => return Err(Error::invalid_length(index, &self)),
+            }
+        }
+
+        // Refuse LEDs with more than four channels
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(Error::invalid_length(5, &self));
+        }
+        Ok(PluginLed(led))
+    }
+}
+
+/// Deserialize a frame returned by the update function of a plugin
+///
+/// Each LED may have three or four channels, and three channel LEDs are made opaque. Returns `None` if the plugin
+/// returned `null`, signalling that it will not provide any more updates.
+pub fn parse_frame(json: &str) -> Result<Option<Frame>, serde_json::Error> {
+    let frame = serde_json::from_str::<Option<Vec<Vec<PluginLed>>>>(json)?;
+    Ok(frame.map(|frame| {
+        frame
+            .into_iter()
+            .map(|row| row.into_iter().map(|led| led.0).collect())
+            .collect()
+    }))
+}
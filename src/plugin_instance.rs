@@ -0,0 +1,337 @@
+use log::Level;
+use simtricks::matrix_config::MatrixConfiguration;
+use simtricks::plugin::{sandbox_hint, PluginMetadata, PluginSource};
+use simtricks::plugin_fps::RequestedFps;
+use simtricks::plugin_latency::LatencySamples;
+use simtricks::plugin_logs::{record_plugin_log, PluginLogBuffer};
+use simtricks::plugin_thread::{
+    PluginCommand, PluginEvent, PluginState, COMMAND_CHANNEL_BOUND, EVENT_CHANNEL_BOUND,
+};
+use simtricks::{Frame, SharedFrame};
+use std::collections::VecDeque;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+
+/// A single plugin being simulated, with its own thread, frame, and history
+pub(crate) struct PluginInstance {
+    /// Where to load the plugin from
+    pub(crate) source: PluginSource,
+
+    /// Where the plugin was loaded from before a test pattern was shown in its place, to go back to afterwards
+    pub(crate) replaced_source: Option<PluginSource>,
+
+    /// The last complete frame retrieved from the plugin, shared with the frame server
+    ///
+    /// Frames are only ever swapped in whole once they have been received from the plugin thread, so readers never see
+    /// a partially written frame or wait for one to be copied.
+    pub(crate) frame: SharedFrame,
+
+    /// The same frame as `frame`, which the UI reads without locking, as only the UI ever replaces the frame
+    latest_frame: Arc<Frame>,
+
+    /// The most recent frames retrieved from the plugin, oldest first
+    history: VecDeque<Arc<Frame>>,
+
+    /// Maximum number of frames to keep in the history
+    history_size: usize,
+
+    /// If true, the plugin thread is automatically generating new frames
+    autoplay: bool,
+
+    /// Number of frames back in the history that is currently being displayed, or zero for the latest frame
+    pub(crate) history_offset: usize,
+
+    /// If true, a new plugin thread should be created
+    pub(crate) restart_pending: bool,
+
+    /// State of the plugin. Unless the plugin is running, do not allow the user to play or step the plugin.
+    state: PluginState,
+
+    /// Commands for the current plugin thread, if there is one
+    commands: Option<SyncSender<PluginCommand>>,
+
+    /// Events from the current plugin thread, if there is one
+    events: Option<Receiver<PluginEvent>>,
+
+    /// Number of frames dropped during autoplay because the plugin was too slow
+    pub(crate) dropped_frames: u64,
+
+    /// Frame rate that the plugin is consistently too slow for, if it is falling behind
+    pub(crate) too_slow_for: Option<f32>,
+
+    /// The last error encountered by the plugin thread, to show to the user
+    pub(crate) error: Option<String>,
+
+    /// The last problem the plugin thread worked around, to show to the user
+    pub(crate) warning: Option<String>,
+
+    /// Frame rate requested by the current plugin, if it requested one
+    pub(crate) requested_fps: RequestedFps,
+
+    /// Durations of the most recent calls to the update function of the current plugin
+    pub(crate) latency: LatencySamples,
+
+    /// The most recent logs made by the current plugin
+    pub(crate) logs: PluginLogBuffer,
+
+    /// Number of frames received from the current plugin thread
+    pub(crate) frame_count: u64,
+
+    /// Metadata provided by the current plugin
+    pub(crate) metadata: PluginMetadata,
+
+    /// Network hosts that the current plugin was allowed to communicate with when it was loaded
+    pub(crate) allowed_hosts: Vec<String>,
+}
+
+impl PluginInstance {
+    /// Create a new plugin instance with a blank frame, without starting a thread for it
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where to load the plugin from
+    /// * `matrix_config` - Configuration of the matrix
+    /// * `history_size` - Maximum number of frames to keep in the history
+    pub(crate) fn new(
+        source: PluginSource,
+        matrix_config: &MatrixConfiguration,
+        history_size: usize,
+    ) -> Self {
+        let latest_frame = Arc::new(matrix_config.blank_frame());
+        Self {
+            source,
+            replaced_source: None,
+            frame: Arc::new(Mutex::new(Arc::clone(&latest_frame))),
+            latest_frame,
+            history: VecDeque::with_capacity(history_size),
+            history_size,
+            autoplay: false,
+            history_offset: 0,
+            restart_pending: true,
+            state: PluginState::Running,
+            commands: None,
+            events: None,
+            dropped_frames: 0,
+            too_slow_for: None,
+            error: None,
+            warning: None,
+            requested_fps: Arc::new(Mutex::new(None)),
+            latency: Arc::default(),
+            logs: Arc::default(),
+            frame_count: 0,
+            metadata: PluginMetadata::default(),
+            allowed_hosts: vec![],
+        }
+    }
+
+    /// Get the current state of the plugin
+    pub(crate) fn state(&self) -> PluginState {
+        self.state
+    }
+
+    /// Set the state of the plugin, i.e. when its thread could not be created
+    pub(crate) fn set_state(&mut self, state: PluginState) {
+        self.state = state;
+    }
+
+    /// Returns true if the plugin is automatically generating new frames
+    pub(crate) fn is_autoplaying(&self) -> bool {
+        self.autoplay
+    }
+
+    /// Start or stop automatically generating new frames
+    pub(crate) fn set_autoplay(&mut self, autoplay: bool) {
+        self.autoplay = autoplay;
+        self.too_slow_for = None;
+        self.send(PluginCommand::SetAutoplay(autoplay));
+    }
+
+    /// Send a command to the current plugin thread, if there is one still listening
+    pub(crate) fn send(&self, command: PluginCommand) {
+        if let Some(commands) = &self.commands {
+            match commands.try_send(command) {
+                Ok(_) => {}
+                Err(TrySendError::Full(command)) => {
+                    log::warn!(
+                        "Plugin thread for {} is busy. Dropping command.",
+                        self.source
+                    );
+                    log::debug!("Dropped the following command: {command:?}");
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    log::debug!("Plugin thread for {} is no longer listening.", self.source);
+                }
+            }
+        }
+    }
+
+    /// Create the channels for a new plugin thread, returning the ends that the thread should hold
+    pub(crate) fn connect(&mut self) -> (Receiver<PluginCommand>, SyncSender<PluginEvent>) {
+        let (command_sender, command_receiver) = sync_channel(COMMAND_CHANNEL_BOUND);
+        let (event_sender, event_receiver) = sync_channel(EVENT_CHANNEL_BOUND);
+        self.commands = Some(command_sender);
+        self.events = Some(event_receiver);
+        (command_receiver, event_sender)
+    }
+
+    /// Act on every event sent by the current plugin thread since this was last called
+    ///
+    /// Returns true if the plugin provided new metadata, so anything naming the plugin should be updated.
+    pub(crate) fn receive_events(&mut self) -> bool {
+        let mut metadata_changed = false;
+        loop {
+            let event = match self.events.as_ref().map(Receiver::try_recv) {
+                Some(Ok(event)) => event,
+                Some(Err(TryRecvError::Disconnected)) => {
+                    self.events = None;
+                    return metadata_changed;
+                }
+                Some(Err(TryRecvError::Empty)) | None => return metadata_changed,
+            };
+
+            match event {
+                PluginEvent::FrameReady(frame) => {
+                    let frame = Arc::new(frame);
+
+                    // Record the new frame in the history, dropping the oldest frame if the history is full
+                    if self.history_size > 0 {
+                        if self.history.len() >= self.history_size {
+                            self.history.pop_front();
+                        }
+                        self.history.push_back(Arc::clone(&frame));
+                    }
+
+                    // Resume after a paused error, now that the plugin has produced a frame
+                    if self.state == PluginState::Paused {
+                        self.state = PluginState::Running;
+                    }
+
+                    self.publish(frame);
+                    self.frame_count += 1;
+                }
+                PluginEvent::Error(error) => {
+                    // Point out a refused host in the plugin's logs, as the plugin can't explain it itself
+                    if let Some(hint) = sandbox_hint(&error) {
+                        log::warn!("{hint}");
+                        record_plugin_log(&self.logs, Level::Warn, hint);
+                    }
+                    self.error = Some(error);
+                }
+                PluginEvent::Warning(warning) => self.warning = Some(warning),
+                PluginEvent::AutoplayStopped(state) => {
+                    self.autoplay = false;
+                    self.too_slow_for = None;
+                    self.state = state;
+                }
+                PluginEvent::FramesDropped(dropped) => self.dropped_frames += dropped,
+                PluginEvent::TooSlow(fps) => self.too_slow_for = fps,
+                PluginEvent::ThreadQuit(state) => self.state = state,
+                PluginEvent::Metadata(metadata) => {
+                    if let Some(description) = metadata.describe() {
+                        log::info!("Loaded plugin {description}.");
+                    }
+                    metadata_changed |= metadata != self.metadata;
+                    self.metadata = metadata;
+                }
+            }
+        }
+    }
+
+    /// Describe what the plugin is doing, for the status bar
+    pub(crate) fn status(&self) -> &'static str {
+        match self.state {
+            PluginState::Running if self.autoplay => "Playing",
+            PluginState::Running => "Paused",
+            PluginState::Paused => "Paused on error",
+            PluginState::Finished => "Finished",
+            PluginState::Errored => "Errored",
+            PluginState::Frozen => "Frozen",
+        }
+    }
+
+    /// Get the number of frames in the history
+    pub(crate) fn history_length(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Name the plugin for the user, using its metadata if it provided any
+    pub(crate) fn label(&self) -> String {
+        self.metadata
+            .describe()
+            .unwrap_or_else(|| self.source.to_string())
+    }
+
+    /// Get the frame to display, from the history if stepping back through it
+    pub(crate) fn displayed_frame(&self) -> Arc<Frame> {
+        match self.history.len().checked_sub(self.history_offset + 1) {
+            Some(index) if self.history_offset > 0 => Arc::clone(&self.history[index]),
+            _ => Arc::clone(&self.latest_frame),
+        }
+    }
+
+    /// Replace the current frame with a blank frame
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix_config` - Configuration of the matrix that the new plugin thread will use
+    pub(crate) fn clear_frame(&mut self, matrix_config: &MatrixConfiguration) {
+        self.publish(Arc::new(matrix_config.blank_frame()));
+    }
+
+    /// Replace the current frame, both for the UI and for anything else sharing it
+    fn publish(&mut self, frame: Arc<Frame>) {
+        // Swap in the new frame, holding the lock only to replace the pointer
+        *self.frame.lock().unwrap() = Arc::clone(&frame);
+        self.latest_frame = frame;
+    }
+
+    /// Stop the current plugin thread, and ask for a new one to be created
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix_config` - Configuration of the matrix that the new plugin thread will use
+    /// * `clear_frame` - If true, blank the current frame. Otherwise, leave it until the new plugin thread replaces it.
+    pub(crate) fn restart(&mut self, matrix_config: &MatrixConfiguration, clear_frame: bool) {
+        if clear_frame {
+            self.clear_frame(matrix_config);
+        }
+        self.stop();
+        self.history_offset = 0;
+        self.restart_pending = true;
+    }
+
+    /// Tell the current plugin thread to quit, and stop listening to it, leaving everything else as it is
+    pub(crate) fn kill(&mut self) {
+        self.send(PluginCommand::Stop);
+        self.commands = None;
+        self.events = None;
+    }
+
+    /// Clear the history, frame and dropped frame counters, error, requested frame rate, update durations, and logs, and
+    /// tell the current plugin thread to quit
+    ///
+    /// The current frame is left in place until the next plugin thread replaces it.
+    fn stop(&mut self) {
+        // Reset the frame counters
+        self.dropped_frames = 0;
+        self.too_slow_for = None;
+        self.frame_count = 0;
+
+        // Forget the last error and warning
+        self.error = None;
+        self.warning = None;
+
+        // Forget the requested frame rate, which the next plugin may request again
+        {
+            *self.requested_fps.lock().unwrap() = None;
+        }
+
+        // Clear the frame history, update durations, and logs
+        self.history.clear();
+        self.latency.lock().unwrap().clear();
+        self.logs.lock().unwrap().clear();
+
+        // Signal that the existing plugin thread should be stopped
+        self.kill();
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Largest number of update durations to keep, dropping the oldest first
+pub const MAX_LATENCY_SAMPLES: usize = 1000;
+
+/// Durations of the most recent calls to the update function of a plugin, in microseconds, oldest first
+pub type LatencySamples = Arc<Mutex<Vec<u128>>>;
+
+/// Record how long a call to the update function took, dropping the oldest sample if there are too many
+pub fn record_latency(samples: &Mutex<Vec<u128>>, duration: Duration) {
+    let mut samples = samples.lock().unwrap();
+    if samples.len() >= MAX_LATENCY_SAMPLES {
+        samples.remove(0);
+    }
+    samples.push(duration.as_micros());
+}
+
+/// Get the given percentile of the samples, using the nearest-rank method, or `None` if there are no samples
+///
+/// # Arguments
+///
+/// * `samples` - The samples, in any order
+/// * `percent` - The percentile to get, from 0 to 100
+pub fn percentile(samples: &[u128], percent: f64) -> Option<u128> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.saturating_sub(1)])
+}
+
+/// Count the samples falling into each of `bins` equal-width bins, spanning from zero to the largest sample
+pub fn histogram(samples: &[u128], bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    let Some(&max) = samples.iter().max() else {
+        return counts;
+    };
+    if bins == 0 {
+        return counts;
+    }
+
+    for &sample in samples {
+        let bin = (sample * bins as u128 / (max + 1)) as usize;
+        counts[bin] += 1;
+    }
+    counts
+}
@@ -1,61 +1,100 @@
-use extism::{CurrentPlugin, InternalExt, UserData, Val};
+use extism::{CurrentPlugin, InternalExt, Val};
+use log::Level;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-/// Make an debug log from a plugin
-pub(crate) fn plugin_debug_log(
-    plugin: &mut CurrentPlugin,
-    inputs: &[Val],
-    _outputs: &mut [Val],
-    _user_data: UserData,
-) -> Result<(), extism::Error> {
-    let message: String = plugin
-        .memory_read_str(inputs[0].i64().unwrap().try_into().unwrap())
-        .unwrap()
-        .to_string();
-    log::debug!(target: "matricks::plugin", "{message}");
-    Ok(())
+/// Maximum number of logs kept from a plugin
+pub const MAX_PLUGIN_LOGS: usize = 500;
+
+/// The most recent logs made by a plugin, with their levels, oldest first
+pub type PluginLogBuffer = Arc<Mutex<VecDeque<(Level, String)>>>;
+
+/// A file to append each log made by a plugin to, as a line of the form `[LEVEL] message`
+pub type PluginLogFile = Arc<Mutex<BufWriter<File>>>;
+
+/// Shared simulator state that the warn and error log functions may act on
+#[derive(Clone, Default)]
+pub struct LogBreakpoint {
+    /// If true, pause the simulator when the plugin makes a warn or error log
+    pub enabled: Arc<AtomicBool>,
+
+    /// Set when the plugin makes a warn or error log while breaking on logs is enabled, until the plugin thread takes it
+    pub triggered: Arc<AtomicBool>,
 }
 
-/// Make an info log from a plugin
-pub(crate) fn plugin_info_log(
-    plugin: &mut CurrentPlugin,
-    inputs: &[Val],
-    _outputs: &mut [Val],
-    _user_data: UserData,
-) -> Result<(), extism::Error> {
-    let message: String = plugin
-        .memory_read_str(inputs[0].i64().unwrap().try_into().unwrap())
-        .unwrap()
-        .to_string();
-    log::info!(target: "matricks::plugin", "{message}");
-    Ok(())
+impl LogBreakpoint {
+    /// Note that the plugin made a log to break on, if breaking on logs is enabled
+    fn trigger(&self) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.triggered.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns true if the plugin made a log to break on since this was last called
+    pub fn take(&self) -> bool {
+        self.triggered.swap(false, Ordering::SeqCst)
+    }
 }
 
-/// Make a warn log from a plugin
-pub(crate) fn plugin_warn_log(
-    plugin: &mut CurrentPlugin,
-    inputs: &[Val],
-    _outputs: &mut [Val],
-    _user_data: UserData,
-) -> Result<(), extism::Error> {
-    let message: String = plugin
-        .memory_read_str(inputs[0].i64().unwrap().try_into().unwrap())
-        .unwrap()
-        .to_string();
-    log::warn!(target: "matricks::plugin", "{message}");
-    Ok(())
+/// Record a log from a plugin in the given buffer, dropping the oldest log if the buffer is full
+pub fn record_plugin_log(log_buffer: &PluginLogBuffer, level: Level, message: String) {
+    let mut logs = log_buffer.lock().unwrap();
+    if logs.len() >= MAX_PLUGIN_LOGS {
+        logs.pop_front();
+    }
+    logs.push_back((level, message));
 }
 
-/// Make an error log from a plugin
-pub(crate) fn plugin_error_log(
+/// Where the logs made by a plugin go, besides the Simtricks logger
+#[derive(Clone)]
+pub(crate) struct PluginLogSinks {
+    /// Shared simulator state to act on when the plugin makes a warn or error log, if any
+    pub(crate) breakpoint: Option<LogBreakpoint>,
+
+    /// Where to keep the most recent logs, if anywhere
+    pub(crate) buffer: Option<PluginLogBuffer>,
+
+    /// File to append each log to, if any
+    pub(crate) file: Option<PluginLogFile>,
+}
+
+/// Append a log from a plugin to the given file, flushing it straight away in case the plugin crashes
+fn write_plugin_log(log_file: &PluginLogFile, level: Level, message: &str) {
+    let mut log_file = log_file.lock().unwrap();
+    if let Err(e) = writeln!(log_file, "[{level}] {message}").and_then(|_| log_file.flush()) {
+        log::debug!("Writing to the plugin log file failed with the following error: {e}");
+    }
+}
+
+/// Make a log from a plugin at the given level
+///
+/// The log is also kept in the log buffer and appended to the log file, if there are any. Warn and error logs trigger
+/// the log breakpoint, if there is one.
+pub(crate) fn plugin_log(
     plugin: &mut CurrentPlugin,
     inputs: &[Val],
-    _outputs: &mut [Val],
-    _user_data: UserData,
+    level: Level,
+    sinks: &PluginLogSinks,
 ) -> Result<(), extism::Error> {
     let message: String = plugin
         .memory_read_str(inputs[0].i64().unwrap().try_into().unwrap())
         .unwrap()
         .to_string();
-    log::error!(target: "matricks::plugin", "{message}");
+    log::log!(target: "matricks::plugin", level, "{message}");
+
+    if let Some(log_file) = &sinks.file {
+        write_plugin_log(log_file, level, &message);
+    }
+    if let Some(log_buffer) = &sinks.buffer {
+        record_plugin_log(log_buffer, level, message);
+    }
+    if level <= Level::Warn {
+        if let Some(log_breakpoint) = &sinks.breakpoint {
+            log_breakpoint.trigger();
+        }
+    }
     Ok(())
 }
@@ -1,105 +1,524 @@
+use crate::capture::FrameCapture;
+use crate::color::average_frames;
+use crate::matrix_config::MatrixConfiguration;
+use crate::plugin::{load_state, next_frame, save_state, setup_plugin, PluginMetadata};
+use crate::plugin_fps::RequestedFps;
+use crate::plugin_latency::{record_latency, LatencySamples};
+use crate::plugin_logs::LogBreakpoint;
+use crate::Frame;
 use extism::Plugin;
-use std::ops::DerefMut;
-use std::str::from_utf8;
-use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TryRecvError};
 use std::time::{Duration, Instant};
 
-pub(crate) fn plugin_thread(
-    mut plugin: Plugin,
-    fps: f32,
-    frame_mutex: Arc<Mutex<Vec<Vec<[u8; 4]>>>>,
-    generate_frame_flag: Arc<Mutex<bool>>,
-    autoplay_flag: Arc<Mutex<bool>>,
-    freeze_flag: Arc<Mutex<bool>>,
-    kill_flag: Arc<Mutex<bool>>,
+/// Number of events a plugin thread can send before the simulator reads them. After that, the thread waits for the
+/// simulator to catch up, so frames can't pile up in memory.
+pub const EVENT_CHANNEL_BOUND: usize = 8;
+
+/// Number of commands the simulator can send before a plugin thread reads them. After that, commands are dropped.
+pub const COMMAND_CHANNEL_BOUND: usize = 32;
+
+/// Number of autoplay updates in a row that must overrun the frame budget before the plugin is reported as too slow
+const OVERRUN_STREAK: u32 = 5;
+
+/// Something that a plugin thread can drive, producing a new frame with each update
+pub trait FrameSource {
+    /// Prepare to produce frames
+    fn setup(&mut self) -> Result<(), Box<dyn Error>>;
+
+    /// Produce the next frame, or `None` if there will be no more frames
+    fn update(&mut self) -> Result<Option<Frame>, Box<dyn Error>>;
+
+    /// Save the state of the source to the given file
+    fn save_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>>;
+
+    /// Restore the state of the source from the given file
+    fn load_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>>;
+
+    /// Get any metadata the source provides about itself, once it is set up
+    fn metadata(&mut self) -> PluginMetadata {
+        PluginMetadata::default()
+    }
+}
+
+impl FrameSource for Plugin<'_> {
+    fn setup(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(setup_plugin(self)?)
+    }
+
+    fn update(&mut self) -> Result<Option<Frame>, Box<dyn Error>> {
+        Ok(next_frame(self)?)
+    }
+
+    fn save_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        save_state(self, path)
+    }
+
+    fn load_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        load_state(self, path)
+    }
+
+    fn metadata(&mut self) -> PluginMetadata {
+        PluginMetadata::read(self)
+    }
+}
+
+impl<T: FrameSource + ?Sized> FrameSource for Box<T> {
+    fn setup(&mut self) -> Result<(), Box<dyn Error>> {
+        (**self).setup()
+    }
+
+    fn update(&mut self) -> Result<Option<Frame>, Box<dyn Error>> {
+        (**self).update()
+    }
+
+    fn save_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        (**self).save_state(path)
+    }
+
+    fn load_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        (**self).load_state(path)
+    }
+
+    fn metadata(&mut self) -> PluginMetadata {
+        (**self).metadata()
+    }
+}
+
+/// State of a plugin, as shown to the user
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PluginState {
+    /// The plugin is running, and can be played, paused, and stepped
+    Running,
+
+    /// The plugin signalled that it will not provide any more updates
+    Finished,
+
+    /// The plugin stopped because of an error
+    Errored,
+
+    /// The plugin paused because of an error, and may be stepped or played to try again
+    Paused,
+
+    /// The plugin could not be loaded
+    Frozen,
+}
+
+impl PluginState {
+    /// Returns true if the plugin can still be asked for new frames
+    pub fn is_live(self) -> bool {
+        matches!(self, PluginState::Running | PluginState::Paused)
+    }
+}
+
+/// A command from the simulator to a plugin thread
+#[derive(Clone, Debug, PartialEq)]
+pub enum PluginCommand {
+    /// Generate the given number of new frames, one after another
+    Step(usize),
+
+    /// Start or stop automatically generating new frames
+    SetAutoplay(bool),
+
+    /// Change the frame rate at which frames are generated during autoplay
+    SetFps(f32),
+
+    /// Save the state of the plugin to the given file
+    SaveState(PathBuf),
+
+    /// Restore the state of the plugin from the given file
+    LoadState(PathBuf),
+
+    /// Quit the thread, without reporting why
+    Stop,
+}
+
+/// An event sent from a plugin thread to the simulator
+#[derive(Clone, Debug, PartialEq)]
+pub enum PluginEvent {
+    /// The plugin produced a new frame
+    FrameReady(Frame),
+
+    /// The plugin thread ran into an error, to show to the user
+    Error(String),
+
+    /// The plugin thread worked around a problem with the plugin, to show to the user
+    Warning(String),
+
+    /// The plugin thread turned off autoplay by itself, leaving the plugin in the given state
+    AutoplayStopped(PluginState),
+
+    /// The plugin thread dropped the given number of frames during autoplay, because the plugin was too slow
+    FramesDropped(u64),
+
+    /// The plugin started consistently overrunning the frame budget at the given frame rate, or stopped overrunning it
+    /// if `None`
+    TooSlow(Option<f32>),
+
+    /// The plugin thread quit, leaving the plugin in the given state
+    ThreadQuit(PluginState),
+
+    /// The plugin was set up, and provided the given metadata
+    Metadata(PluginMetadata),
+}
+
+/// Settings of a plugin thread, fixed for the life of the thread
+pub struct PluginThreadConfig {
+    /// Configuration of the matrix, as passed to the plugin
+    pub matrix_config: MatrixConfiguration,
+
+    /// Frames per second at which to generate frames during autoplay, until told otherwise
+    pub fps: f32,
+
+    /// Number of frames to generate and discard after setting up the plugin
+    pub warmup_frames: usize,
+
+    /// If true, stop the plugin if its setup fails, rather than running it anyway
+    pub strict_setup: bool,
+
+    /// If true, stop the plugin if it returns a frame that doesn't fit the matrix, rather than padding or trimming it
+    pub strict_dimensions: bool,
+
+    /// Number of recent frames to average together before showing a frame, or 1 to show each frame as is
+    pub smoothing_frames: usize,
+
+    /// If true, generate frames at the frame rate requested by the plugin, if it requested one
+    pub respect_plugin_fps: bool,
+
+    /// If true, pause the plugin when an update fails, rather than stopping it
+    pub pause_on_error: bool,
+
+    /// If given, save every Nth frame from the plugin as a PNG image
+    pub capture: Option<FrameCapture>,
+}
+
+/// Channels and shared values connecting a plugin thread to the simulator
+pub struct PluginThreadState {
+    /// Commands from the simulator. The thread quits when the sending side is dropped.
+    pub commands: Receiver<PluginCommand>,
+
+    /// Events for the simulator. Sending waits while the channel is full, until the simulator reads or drops it.
+    pub events: SyncSender<PluginEvent>,
+
+    /// Frame rate requested by the plugin through `matricks_set_target_fps`, if it requested one
+    pub requested_fps: RequestedFps,
+
+    /// Durations of the most recent calls to the update function of the plugin
+    pub latency: LatencySamples,
+
+    /// Log breakpoint given to the plugin, checked after each update to see whether autoplay should be turned off
+    pub log_breakpoint: Option<LogBreakpoint>,
+
+    /// Called whenever the plugin thread sends an event, so the simulator can redraw
+    pub on_new_frame: Option<Box<dyn Fn() + Send>>,
+}
+
+/// Drive a plugin from a separate thread, as controlled by the commands it receives
+///
+/// The thread blocks until a command arrives, or during autoplay until the next frame is due, so it uses no CPU while
+/// paused. It quits when told to stop, or when the simulator drops its end of the command channel.
+///
+/// # Arguments
+///
+/// * `plugin` - The plugin, or other source of frames, to drive
+/// * `config` - Settings of the thread
+/// * `state` - Channels and values shared with the simulator
+pub fn plugin_thread(
+    mut plugin: impl FrameSource,
+    config: PluginThreadConfig,
+    state: PluginThreadState,
 ) {
-    // Setup frame timing variables
+    let PluginThreadConfig {
+        matrix_config,
+        mut fps,
+        warmup_frames,
+        strict_setup,
+        strict_dimensions,
+        smoothing_frames,
+        respect_plugin_fps,
+        pause_on_error,
+        capture,
+    } = config;
+    let PluginThreadState {
+        commands,
+        events,
+        requested_fps: requested_fps_mutex,
+        latency: latency_mutex,
+        log_breakpoint,
+        on_new_frame,
+    } = state;
+    let send = |event: PluginEvent| {
+        // If the simulator has stopped listening, the next command will be a disconnect
+        if events.send(event).is_ok() {
+            if let Some(on_new_frame) = &on_new_frame {
+                on_new_frame();
+            }
+        }
+    };
+
+    // Keep the most recent frames to average together, if smoothing
+    let smoothing_frames = smoothing_frames.max(1);
+    let mut recent_frames: VecDeque<Frame> = VecDeque::with_capacity(smoothing_frames);
+
+    // Setup frame timing and counting variables. Frames are due a fixed time after the previous update started, so the
+    // time taken by the plugin doesn't slow down the frame rate.
     let mut time_at_last_frame = Instant::now();
-    let time_between_frames = Duration::from_secs_f32(1.0 / fps);
+    let mut autoplay = false;
+    let mut frame_count: u64 = 0;
+    let mut warned_about_dimensions = false;
+    let mut overrun_streak: u32 = 0;
+    let mut pending_steps: usize = 0;
 
-    // Call setup function of current active plugin
-    match plugin.call("setup", "") {
-        Ok(_) => {
-            log::info!("Successfully set up plugin.");
+    // Call setup function of current active plugin, letting the simulator know if it failed
+    if let Err(e) = plugin.setup() {
+        send(PluginEvent::Error(format!("Plugin setup failed: {e}")));
+        if strict_setup {
+            log::info!("Freezing simulator.");
+            send(PluginEvent::ThreadQuit(PluginState::Frozen));
+            return;
         }
-        Err(e) => {
-            log::warn!("Failed to set up plugin.");
-            log::debug!("Failed to set up plugin with following error: {e}");
+    }
+
+    // Read the metadata of the plugin now that it is set up, as it may depend on the config
+    send(PluginEvent::Metadata(plugin.metadata()));
+
+    // Warm up the plugin before showing any of its frames
+    if let Err((end_state, e)) = warm_up(&mut plugin, warmup_frames) {
+        log::info!("Freezing simulator.");
+        if let Some(e) = e {
+            send(PluginEvent::Error(e));
         }
-    };
+        send(PluginEvent::ThreadQuit(end_state));
+        return;
+    }
 
-    'update_loop: loop {
-        // Kill the thread if requested
-        {
-            if *kill_flag.lock().unwrap() {
-                log::info!("Received kill signal.");
-                break 'update_loop;
+    // Run the plugin until it stops, noting why it stopped (or `None` if it was told to stop)
+    let end_state = 'update_loop: loop {
+        // Follow the current frame rate, or the frame rate requested by the plugin if asked to
+        let requested_fps = *requested_fps_mutex.lock().unwrap();
+        let target_fps = match requested_fps {
+            Some(requested_fps) if respect_plugin_fps => requested_fps,
+            _ => fps,
+        };
+        let time_between_frames = Duration::from_secs_f32(1.0 / target_fps);
+
+        // Wait for the next command, or during autoplay, until the next frame is due. Don't wait at all while stepping.
+        let command = if pending_steps > 0 {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break 'update_loop None,
+            }
+        } else if autoplay {
+            let wait = time_between_frames.saturating_sub(time_at_last_frame.elapsed());
+            match commands.recv_timeout(wait) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break 'update_loop None,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break 'update_loop None,
+            }
+        };
+
+        // Act on the command, counting the frames that the simulator wants us to generate
+        match command {
+            Some(PluginCommand::Step(steps)) => pending_steps += steps,
+            Some(PluginCommand::SetAutoplay(new_autoplay)) => {
+                // Start counting overruns afresh, as the simulator forgets them when autoplay changes
+                autoplay = new_autoplay;
+                overrun_streak = 0;
             }
+            Some(PluginCommand::SetFps(new_fps)) => fps = new_fps,
+            Some(PluginCommand::SaveState(path)) => match plugin.save_state(&path) {
+                Ok(_) => log::info!("Saved plugin state to {}.", path.display()),
+                Err(e) => {
+                    log::warn!("Failed to save plugin state to {}.", path.display());
+                    log::debug!("Saving plugin state failed with the following error: {e}");
+                }
+            },
+            Some(PluginCommand::LoadState(path)) => match plugin.load_state(&path) {
+                Ok(_) => log::info!("Restored plugin state from {}.", path.display()),
+                Err(e) => {
+                    log::warn!("Failed to restore plugin state from {}.", path.display());
+                    log::debug!("Restoring plugin state failed with the following error: {e}");
+                }
+            },
+            Some(PluginCommand::Stop) => {
+                log::info!("Received stop command.");
+                break 'update_loop None;
+            }
+            None => {}
         }
 
+        // Take one of the requested frames, if there are any left
+        let frame_requested = pending_steps > 0;
+        pending_steps = pending_steps.saturating_sub(1);
+
         if (
                 // Is autoplay on, and has enough time passes for the given FPS?
-            *autoplay_flag.lock().unwrap()
+            autoplay
             && (Instant::now().duration_since(time_at_last_frame) >= time_between_frames))
                 // Or, does the simulator want us to generate a new frame?
-            || *generate_frame_flag.lock().unwrap()
+            || frame_requested
         {
-            // Reset the frame generate flag
-            if *generate_frame_flag.lock().unwrap() {
-                *generate_frame_flag.lock().unwrap() = false;
-            }
-
-            // Attempt to pull the next frame from the plugin, as a UTF8 JSON string
-            let new_state_utf8 = match plugin.call("update", "") {
-                Ok(utf8) => utf8,
+            // Attempt to pull the next frame from the plugin, timing how long the plugin takes
+            let time_at_update = Instant::now();
+            time_at_last_frame = time_at_update;
+            let update = plugin.update();
+            let update_duration = time_at_update.elapsed();
+            log::trace!("Plugin update took {update_duration:?}.");
+            record_latency(&latency_mutex, update_duration);
+            let new_state: Option<Vec<Vec<[u8; 4]>>> = match update {
+                Ok(update) => update,
                 Err(e) => {
-                    log::error!("Failed to receive update from plugin.");
-                    log::debug!(
-                        "Received the following error while polling for update from plugin: {e}"
-                    );
-                    break 'update_loop;
-                }
-            };
+                    log::error!("{e}");
+                    send(PluginEvent::Error(e.to_string()));
+                    if !pause_on_error {
+                        break 'update_loop Some(PluginState::Errored);
+                    }
 
-            // Convert the UTF8 to a string
-            let new_state_str = match from_utf8(new_state_utf8) {
-                Ok(str) => str,
-                Err(e) => {
-                    log::error!("Failed to convert update from UTF8.");
-                    log::debug!("Received the following error while converting from UTF8: {e}");
-                    break 'update_loop;
+                    // Pause rather than stopping, so the plugin can be stepped to try again
+                    log::info!("Pausing simulator due to plugin error.");
+                    autoplay = false;
+                    overrun_streak = 0;
+                    send(PluginEvent::AutoplayStopped(PluginState::Paused));
+                    continue 'update_loop;
                 }
             };
 
-            // Deserialize the new state from a string
-            let new_state: Option<Vec<Vec<[u8; 4]>>> =
-                match serde_json::from_str::<Option<Vec<Vec<[u8; 4]>>>>(new_state_str) {
-                    Ok(update) => update,
-                    Err(_) => {
-                        log::error!("Invalid update returned from plugin.");
-                        break 'update_loop;
-                    }
-                };
+            // Pause if the plugin made a log that the simulator should break on
+            if log_breakpoint.as_ref().is_some_and(LogBreakpoint::take) && autoplay {
+                log::info!("Pausing simulator due to plugin log.");
+                autoplay = false;
+                overrun_streak = 0;
+                send(PluginEvent::AutoplayStopped(PluginState::Running));
+            }
+
+            // Count the frames dropped if the plugin took longer than the frame budget during autoplay
+            if autoplay && update_duration > time_between_frames {
+                let overrun = update_duration - time_between_frames;
+                let dropped = (update_duration.as_secs_f64() / time_between_frames.as_secs_f64())
+                    .floor() as u64;
+                log::debug!(
+                    "Plugin update took {update_duration:?}, overrunning the frame budget by {overrun:?}."
+                );
+                send(PluginEvent::FramesDropped(dropped));
+
+                // Warn once the plugin has overrun enough updates in a row to be consistently behind
+                overrun_streak += 1;
+                if overrun_streak == OVERRUN_STREAK {
+                    log::warn!("Dropping frames, as the plugin is too slow for {target_fps} FPS.");
+                    send(PluginEvent::TooSlow(Some(target_fps)));
+                }
+            } else if autoplay && overrun_streak > 0 {
+                // Let the simulator know the plugin has caught up, if it was reported as too slow
+                if overrun_streak >= OVERRUN_STREAK {
+                    log::info!("Plugin is keeping up with {target_fps} FPS again.");
+                    send(PluginEvent::TooSlow(None));
+                }
+                overrun_streak = 0;
+            }
 
             // If the plugin signalled that it is done, exit this thread
-            let new_state: Vec<Vec<[u8; 4]>> = match new_state {
+            let mut new_state: Vec<Vec<[u8; 4]>> = match new_state {
                 Some(new_state) => new_state,
                 None => {
                     log::info!("Plugin has stopped providing updates.");
-                    break 'update_loop;
+                    break 'update_loop Some(PluginState::Finished);
                 }
             };
 
-            // Replace the previous frame with the new frame
-            let mut frame = frame_mutex.lock().unwrap();
-            let frame = frame.deref_mut();
-            *frame = new_state;
+            // Make sure the frame fits the matrix, padding or trimming it unless asked to be strict
+            if !matrix_config.fits(&new_state) {
+                let message = format!(
+                    "Plugin returned a {}x{} frame, but the matrix is {}x{}.",
+                    new_state.first().map_or(0, |row| row.len()),
+                    new_state.len(),
+                    matrix_config.width,
+                    matrix_config.height
+                );
+                if strict_dimensions {
+                    log::error!("{message}");
+                    send(PluginEvent::Error(message));
+                    break 'update_loop Some(PluginState::Errored);
+                }
+
+                // Only warn the first time, rather than for every frame
+                if !warned_about_dimensions {
+                    log::warn!("{message} Padding or trimming the frame to fit.");
+                    send(PluginEvent::Warning(message));
+                    warned_about_dimensions = true;
+                }
+                new_state = matrix_config.fit_frame(new_state);
+            }
+
+            // Average the frame with the frames before it, if smoothing
+            if smoothing_frames > 1 {
+                if recent_frames.len() >= smoothing_frames {
+                    recent_frames.pop_front();
+                }
+                recent_frames.push_back(new_state);
+                new_state = average_frames(&recent_frames);
+            }
+
+            // Capture the new frame, if it is due
+            frame_count += 1;
+            if let Some(capture) = capture
+                .as_ref()
+                .filter(|capture| capture.is_due(frame_count))
+            {
+                match capture.capture(&new_state, frame_count) {
+                    Ok(path) => log::debug!("Captured frame {frame_count} to {}.", path.display()),
+                    Err(e) => {
+                        log::warn!("Failed to capture frame {frame_count}.");
+                        log::debug!("Capturing frame failed with the following error: {e}");
+                    }
+                }
+            }
+
+            // Hand the new frame to the simulator
+            send(PluginEvent::FrameReady(new_state));
+        }
+    };
+
+    // Let the simulator know why the plugin stopped. A stopped thread sends nothing, as a new thread may already be
+    // running.
+    if let Some(end_state) = end_state {
+        log::info!("Freezing simulator.");
+        send(PluginEvent::ThreadQuit(end_state));
+    }
+}
 
-            // Mark the time
-            time_at_last_frame = Instant::now();
+/// Generate and discard the given number of frames from the plugin
+///
+/// If the plugin stops during warm-up, returns the state the plugin stopped in, and the error that stopped it if any.
+fn warm_up(
+    plugin: &mut impl FrameSource,
+    warmup_frames: usize,
+) -> Result<(), (PluginState, Option<String>)> {
+    if warmup_frames == 0 {
+        return Ok(());
+    }
+
+    for warmup_frame in 1..=warmup_frames {
+        match plugin.update() {
+            Ok(Some(_)) => log::debug!("Discarded warm-up frame {warmup_frame}/{warmup_frames}."),
+            Ok(None) => {
+                log::info!("Plugin stopped providing updates during warm-up.");
+                return Err((PluginState::Finished, None));
+            }
+            Err(e) => {
+                log::error!("Failed to warm up plugin. {e}");
+                return Err((PluginState::Errored, Some(e.to_string())));
+            }
         }
     }
 
-    log::info!("Freezing simulator.");
-    *freeze_flag.lock().unwrap() = true;
+    log::info!("Finished warming up plugin after {warmup_frames} frames.");
+    Ok(())
 }
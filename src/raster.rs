@@ -0,0 +1,49 @@
+use crate::color::ChannelOrder;
+
+/// An image of a frame, with each LED drawn as a square block of pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct RasterImage {
+    /// Width of the image, in pixels
+    pub width: usize,
+
+    /// Height of the image, in pixels
+    pub height: usize,
+
+    /// Pixels of the image in RGBA order, row by row from the top left
+    pub pixels: Vec<u8>,
+}
+
+/// Draw a frame as an RGBA image, with each LED as a square of `led_size` by `led_size` pixels
+///
+/// The frame is sized by its first row, and any missing LEDs are left blank.
+pub fn rasterize_frame(
+    frame: &[Vec<[u8; 4]>],
+    channel_order: ChannelOrder,
+    led_size: usize,
+) -> RasterImage {
+    let columns = frame.first().map_or(0, |row| row.len());
+    let width = columns * led_size;
+    let height = frame.len() * led_size;
+    let mut pixels = vec![0; width * height * 4];
+
+    let [red, green, blue, alpha] = channel_order;
+    for (y, row) in frame.iter().enumerate() {
+        for (x, led) in row.iter().enumerate().take(columns) {
+            let color = [led[red], led[green], led[blue], led[alpha]];
+
+            // Fill the block of pixels covered by this LED
+            for pixel_y in y * led_size..(y + 1) * led_size {
+                let start = (pixel_y * width + x * led_size) * 4;
+                for pixel in pixels[start..start + led_size * 4].chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    RasterImage {
+        width,
+        height,
+        pixels,
+    }
+}
@@ -0,0 +1,80 @@
+use crate::color::ChannelOrder;
+use crate::raster::rasterize_frame;
+use crate::Frame;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, RgbaImage};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Frames recorded from a plugin, along with when each frame was recorded
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    /// The recorded frames, oldest first
+    frames: Vec<Frame>,
+
+    /// When each frame was recorded
+    timestamps: Vec<Instant>,
+}
+
+impl Recording {
+    /// Create a new, empty recording
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a frame to the end of the recording, shown from now until the next frame is added
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+        self.timestamps.push(Instant::now());
+    }
+
+    /// Get the number of frames in the recording
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if no frames have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Save the recording as a looping GIF, with each LED as a square of `led_size` by `led_size` pixels
+    ///
+    /// Each frame is shown for as long as it was shown while recording, and the last frame is shown until now.
+    pub fn save_gif(
+        &self,
+        channel_order: ChannelOrder,
+        led_size: usize,
+        path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.is_empty() {
+            return Err("no frames were recorded".into());
+        }
+
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let now = Instant::now();
+        for (index, frame) in self.frames.iter().enumerate() {
+            // Show the frame until the next frame was recorded
+            let shown_until = self.timestamps.get(index + 1).copied().unwrap_or(now);
+            let duration: Duration = shown_until.duration_since(self.timestamps[index]);
+
+            let raster = rasterize_frame(frame, channel_order, led_size);
+            let image =
+                RgbaImage::from_raw(raster.width as u32, raster.height as u32, raster.pixels)
+                    .ok_or("frame could not be converted to an image")?;
+            encoder.encode_frame(image::Frame::from_parts(
+                image,
+                0,
+                0,
+                Delay::from_saturating_duration(duration),
+            ))?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,297 +1,2776 @@
-use crate::plugin_logs;
-use crate::plugin_thread::plugin_thread;
-use eframe::egui::{Context, Key, Modifiers, Pos2, Rect, Rounding, Sense, Vec2};
+use crate::clargs::AlphaMode;
+use crate::plugin_instance::PluginInstance;
+use arboard::{Clipboard, ImageData};
+use clap::ValueEnum;
+use eframe::egui::{
+    Align2, Color32, ColorImage, Context, FontId, Id, Key, Mesh, Modifiers, Painter, Pos2, Rect,
+    Rounding, Sense, Shape, TextureHandle, TextureOptions, Ui, Vec2,
+};
 use eframe::emath::RectTransform;
 use eframe::{egui, App, Frame};
-use extism::manifest::Wasm;
-use extism::{Function, Manifest, Plugin, ValType};
-use std::collections::BTreeMap;
+use extism::Plugin;
+use log::Level;
+use simtricks::capture::{frame_to_rust, FrameCapture};
+use simtricks::color::{
+    blend_over, bloom_frame, channel_difference, channel_stats, color_histogram, gamma_table,
+    histogram_bucket_color, led_hex, most_common_colors, palette, ChannelOrder, ChannelStats,
+    ColorLut, DEFAULT_CHANNEL_ORDER,
+};
+use simtricks::display_transform::{DisplayTransform, FrameTransform};
+use simtricks::frame_dump::{load_frames, FramePlayer};
+use simtricks::frame_server::FrameServer;
+use simtricks::matrix_config::{MatrixConfiguration, RequestedDimensions};
+use simtricks::path_map::PathMap;
+use simtricks::plugin::{
+    create_plugin, preferred_dimensions, PluginMetadata, PluginOptions, PluginSource,
+};
+use simtricks::plugin_latency::{histogram, percentile};
+use simtricks::plugin_logs::{LogBreakpoint, PluginLogFile};
+use simtricks::plugin_thread::{
+    plugin_thread, FrameSource, PluginCommand, PluginState, PluginThreadConfig, PluginThreadState,
+};
+use simtricks::raster::rasterize_frame;
+use simtricks::recording::Recording;
+use simtricks::test_pattern::{PatternPlayer, TestPattern};
+use std::borrow::Cow;
 use std::error::Error;
-use std::fs::read;
-use std::ops::DerefMut;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-/// A simulator for a single Matricks plugin
+/// Color of the badge showing a problem the plugin thread worked around
+const WARNING_BADGE_COLOR: Color32 = Color32::from_rgb(140, 90, 0);
+
+/// Size of the frame history above which to warn the user, in bytes
+const HISTORY_MEMORY_WARNING_BYTES: usize = 100 * 1024 * 1024;
+
+/// Gamma applied to the displayed colors when gamma correction is first enabled
+const DEFAULT_GAMMA: f32 = 1.0;
+
+/// Largest width or height the matrix may be resized to from the UI
+const MAX_MATRIX_SIDELENGTH: usize = 1024;
+
+/// Largest frame rate that can be chosen from the UI
+const MAX_FPS: f32 = 240.0;
+
+/// Change in frame rate when '[' or ']' is pressed
+const FPS_STEP: f32 = 1.0;
+
+/// Largest number of frames that can be advanced by a single step
+const MAX_STEP_SIZE: usize = 1000;
+
+/// Number of frames advanced when Shift+Right is pressed
+const JUMP_STEP_SIZE: usize = 10;
+
+/// Largest radius of the corners of each LED that can be chosen from the UI, in points
+const MAX_LED_ROUNDING: f32 = 32.0;
+
+/// Width of each LED after a 1:1 snap when first opened, in pixels
+const DEFAULT_SNAP_LED_PIXELS: u32 = 10;
+
+/// Largest width of each LED that can be chosen for a 1:1 snap, in pixels
+const MAX_SNAP_LED_PIXELS: u32 = 100;
+
+/// Size of each LED relative to its cell when bloom is enabled, leaving room for the glow to show around it
+const BLOOM_LED_SCALE: f32 = 0.7;
+
+/// Extensions of the files that can be opened as plugins, in any case
+const PLUGIN_EXTENSIONS: [&str; 2] = ["wasm", "wat"];
+
+/// Name of the file in the capture directory that the state of a plugin is saved to and restored from
+const SNAPSHOT_FILE_NAME: &str = "snapshot.bin";
+
+/// Key that steps forward, as does the right arrow
+const STEP_KEY: Key = Key::N;
+
+/// Key that steps back, as does the left arrow
+const STEP_BACK_KEY: Key = Key::B;
+
+/// Key that restores the state of the plugin from the snapshot
+const LOAD_STATE_KEY: Key = Key::L;
+
+/// Key that restarts the plugin
+const RESTART_KEY: Key = Key::R;
+
+/// Key that shows or hides the update latency histogram
+const LATENCY_PANEL_KEY: Key = Key::H;
+
+/// Key that shows or hides the output logged by the plugin
+const LOG_PANEL_KEY: Key = Key::O;
+
+/// Key that shows or hides the colors of the frame
+const COLOR_PANEL_KEY: Key = Key::P;
+
+/// Key that shows or hides the values of each channel of the frame
+const CHANNEL_PANEL_KEY: Key = Key::V;
+
+/// Key that starts or stops recording a GIF
+const RECORD_KEY: Key = Key::G;
+
+/// Key that switches to the next alpha mode
+const ALPHA_MODE_KEY: Key = Key::A;
+
+/// Letter keys bound to shortcuts of their own, which can't be chosen as the snapshot key
+pub(crate) const LETTER_SHORTCUTS: [Key; 10] = [
+    STEP_KEY,
+    STEP_BACK_KEY,
+    LOAD_STATE_KEY,
+    RESTART_KEY,
+    LATENCY_PANEL_KEY,
+    LOG_PANEL_KEY,
+    COLOR_PANEL_KEY,
+    CHANNEL_PANEL_KEY,
+    RECORD_KEY,
+    ALPHA_MODE_KEY,
+];
+
+/// Time between repaints when redrawing continuously, for about 60 repaints per second
+const CONTINUOUS_REPAINT_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How long to show a toast for
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+/// Height reserved above each matrix for the name of the plugin, when comparing several plugins
+const PLUGIN_LABEL_HEIGHT: f32 = 20.0;
+
+/// Space left between matrices, when comparing several plugins
+const PLUGIN_SPACING: f32 = 8.0;
+
+/// Largest number of colors copied by the "Copy Palette" button
+const PALETTE_SIZE: usize = 16;
+
+/// Width and height of each LED in an image copied to the clipboard, in pixels
+const CLIPBOARD_LED_SIZE: usize = 16;
+
+/// Width and height of each LED in a recorded GIF, in pixels
+const RECORDING_LED_SIZE: usize = 16;
+
+/// Number of bars in the histogram of update durations
+const LATENCY_BINS: usize = 40;
+
+/// Size of the histogram of update durations, in points
+const LATENCY_PLOT_SIZE: Vec2 = Vec2::new(320.0, 120.0);
+
+/// Number of ranges each color channel is split into for the color histogram, giving this many cubed bars
+const COLOR_HISTOGRAM_LEVELS: usize = 4;
+
+/// Size of the color histogram, in points
+const COLOR_PLOT_SIZE: Vec2 = Vec2::new(320.0, 120.0);
+
+/// Number of most common colors shown as swatches in the color panel
+const COLOR_SWATCHES: usize = 8;
+
+/// Width and height of each swatch in the color panel, in points
+const COLOR_SWATCH_SIZE: Vec2 = Vec2::new(24.0, 24.0);
+
+/// Shortest time between recomputing the colors of the frame, so large matrices at high frame rates stay cheap
+const COLOR_STATS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Size of the plugin log panel when first shown, in points
+const LOG_PANEL_SIZE: Vec2 = Vec2::new(480.0, 240.0);
+
+/// How much the difference in each channel is scaled up in the diff overlay, so that small differences stand out
+const DIFF_OVERLAY_SCALE: u8 = 4;
+
+/// Display settings that can be changed while the simulator runs, without affecting the plugins
+///
+/// Each field holds a copy of the simulator's field of the same name.
+#[derive(Clone, Default)]
+struct DisplaySettings {
+    fps: f32,
+    gamma_enabled: bool,
+    gamma: f32,
+    lut_enabled: bool,
+    led_rounding: f32,
+    alpha_mode: AlphaMode,
+    blend_background: bool,
+    background: [u8; 3],
+    bloom_enabled: bool,
+    diff_threshold: u8,
+    diff_overlay: bool,
+}
+
+/// How the two plugins differ, as shown in the diff view
+struct Difference {
+    /// Frame with matching LEDs black and differing LEDs lit in proportion to how different they are
+    frame: simtricks::Frame,
+
+    /// Number of LEDs with a channel that differs by more than the threshold
+    differing_leds: usize,
+
+    /// Sum of the absolute differences between every channel of every LED
+    total: u64,
+}
+
+/// Colors of a frame, as shown in the color panel
+struct ColorStats {
+    /// The frame that the colors were counted from
+    frame: Arc<simtricks::Frame>,
+
+    /// When the colors were counted
+    counted_at: Instant,
+
+    /// Number of LEDs in each bucket of the color histogram
+    histogram: Vec<usize>,
+
+    /// The most common colors in RGBA order, with the number of LEDs of each, most common first
+    top_colors: Vec<([u8; 4], usize)>,
+}
+
+/// How several plugins are shown
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum PluginView {
+    /// Show every plugin at once, played, stepped, and restarted in lockstep
+    #[default]
+    SideBySide,
+
+    /// Show one plugin at a time, each with its own controls
+    Tabs,
+
+    /// Show two plugins side by side in lockstep, along with a matrix highlighting the LEDs where they differ
+    Diff,
+}
+
+/// A texture holding the LEDs of a matrix as last drawn, so that only the LEDs that change need to be uploaded again
+#[derive(Clone)]
+struct MatrixTexture {
+    /// The texture, with one texel per LED
+    texture: TextureHandle,
+
+    /// Color of each LED in the texture, row by row
+    colors: Vec<Color32>,
+}
+
+/// A simulator for one or more Matricks plugins
 pub(crate) struct Simulator {
-    /// Path to the plugin to simulate
-    path: PathBuf,
+    /// The plugins to simulate
+    plugins: Vec<PluginInstance>,
+
+    /// How the plugins are shown
+    view: PluginView,
+
+    /// Index of the plugin shown and controlled in the tab view
+    selected_plugin: usize,
+
+    /// Largest difference between the channels of two LEDs for them to be considered matching in the diff view
+    diff_threshold: u8,
+
+    /// If true, the diff view shows only the difference between the plugins, with each channel of each LED showing how
+    /// different that channel is
+    diff_overlay: bool,
+
+    /// Network hosts that the plugin may communicate with, from the next restart if they have been changed
+    allowed_hosts: Vec<String>,
+
+    /// Host typed into the Plugin menu, to be added to the allowed hosts
+    new_allowed_host: String,
+
+    /// Map a location on the host filesystem to the plugin filesystem
+    path_maps: Vec<PathMap>,
+
+    /// Extra variables for the plugins, as keys and values
+    vars: Vec<(String, String)>,
+
+    /// Maximum number of frames to keep in the history
+    history_size: usize,
+
+    /// Number of frames to generate and discard when the plugin starts
+    warmup_frames: usize,
+
+    /// Configuration of the matrix, passed to the plugins
+    matrix_config: MatrixConfiguration,
+
+    /// Dimensions of the matrix being edited by the user, applied with `resize_matrix`
+    pending_dimensions: (usize, usize),
+
+    /// Transform applied to the matrix when it is displayed
+    display_transform: DisplayTransform,
+
+    /// The display transform, composed into a single function when the plugin thread is spawned
+    frame_transform: FrameTransform,
+
+    /// If true, apply gamma correction to the displayed colors
+    gamma_enabled: bool,
+
+    /// Gamma applied to the displayed colors, when gamma correction is enabled
+    gamma: f32,
+
+    /// Lookup tables that remap the displayed red, green, and blue values, if one was loaded
+    lut: Option<ColorLut>,
+
+    /// If true, remap the displayed colors with the lookup tables
+    lut_enabled: bool,
+
+    /// File to append each log made by the plugins to, if any
+    plugin_log_file: Option<PluginLogFile>,
+
+    /// Color used by the test patterns, in RGBA order
+    test_pattern_color: [u8; 4],
+
+    /// Aspect ratio of a single LED (width, then height), reduced so that the smaller of the two is 1
+    led_aspect: (f32, f32),
+
+    /// Index of the red, green, blue, and alpha channels within each LED of a frame
+    channel_order: ChannelOrder,
+
+    /// If true, draw plain LEDs from a cached texture, uploading only the LEDs that changed. Otherwise, draw every LED
+    /// as its own rectangle.
+    dirty_tracking: bool,
+
+    /// If true, draw a soft glow around each LED, like the light bleed of a real LED panel
+    bloom_enabled: bool,
+
+    /// Radius of the corners of each LED, in points
+    led_rounding: f32,
+
+    /// How the alpha channel of each LED is displayed
+    alpha_mode: AlphaMode,
+
+    /// If true, turn off autoplay when the plugin makes a warn or error log
+    break_on_log: Arc<AtomicBool>,
+
+    /// If true, ask the user to confirm before restarting a running plugin
+    confirm_restart: bool,
+
+    /// If true, the restart confirmation dialog is open
+    restart_dialog_open: bool,
+
+    /// Dimensions of the matrix given by the user, where a missing width or height is taken from the plugin if it
+    /// declares its dimensions
+    requested_dimensions: RequestedDimensions,
+
+    /// If true, stop a plugin whose setup fails, rather than running it anyway
+    strict_setup: bool,
+
+    /// If true, stop a plugin that returns a frame that doesn't fit the matrix, rather than padding or trimming it
+    strict_dimensions: bool,
+
+    /// If true, blank the matrix on restart. Otherwise, leave the last frame in place until the first new frame arrives.
+    clear_on_restart: bool,
+
+    /// Server streaming the frames of the first plugin to WebSocket clients, if started
+    frame_server: Option<FrameServer>,
+
+    /// System clipboard, opened on the first copy and kept so that copied images outlive the copy on every platform
+    clipboard: Option<Clipboard>,
+
+    /// If true, pause a plugin when an update fails, rather than stopping it
+    pause_on_error: bool,
+
+    /// Frames per second at which the plugins generate frames during autoplay, which may be changed without a restart
+    fps: f32,
+
+    /// Number of recent frames to average together before showing a frame, or 1 to show each frame as is
+    smoothing_frames: usize,
+
+    /// If true, run each plugin at the frame rate it requests, rather than the target FPS of the matrix
+    respect_plugin_fps: bool,
+
+    /// If true, redraw the window about 60 times per second. Otherwise, only redraw when a plugin installs a new frame or
+    /// the user interacts with the window.
+    continuous_repaint: bool,
+
+    /// Area of the window that the matrix was last drawn in
+    matrix_area: Rect,
+
+    /// Width of each LED after a 1:1 snap, in pixels
+    snap_led_pixels: u32,
+
+    /// If true, resize the window for a 1:1 snap on the next update
+    snap_requested: bool,
+
+    /// Key that saves the state of the plugin to a file
+    snapshot_key: Key,
+
+    /// Number of frames between automatic captures of each plugin, if capturing
+    capture_every: Option<u64>,
+
+    /// Directory to save automatic captures in
+    capture_dir: PathBuf,
+
+    /// Path or URL typed into the dialog for opening a different plugin, if the dialog is open
+    open_dialog: Option<String>,
+
+    /// If true, stop the plugin threads and close the window on the next update
+    quit_requested: bool,
+
+    /// If true, start or stop recording a GIF once the keyboard shortcuts have been handled
+    record_toggle_requested: bool,
+
+    /// If true, copy the current frame to the clipboard once the keyboard shortcuts have been handled
+    frame_copy_requested: bool,
+
+    /// If true, show only the matrix, in borderless fullscreen
+    fullscreen: bool,
+
+    /// Whether the window was last asked to be fullscreen, so that it is only asked again when fullscreen is toggled
+    window_fullscreen: bool,
+
+    /// If true, blend each LED over the background color, rather than over the window
+    blend_background: bool,
+
+    /// Color of the surface behind the matrix, when blending over the background
+    background: [u8; 3],
+
+    /// Number of frames advanced by each step
+    step_size: usize,
+
+    /// If true, show a histogram of how long the first controlled plugin takes to update
+    latency_panel_open: bool,
+
+    /// If true, show a histogram and the most common colors of the displayed frame of the first controlled plugin
+    color_panel_open: bool,
+
+    /// Colors of the frame last counted for the color panel
+    color_stats: Option<ColorStats>,
+
+    /// If true, show the lowest, highest, and mean values of each channel of the displayed frame in a side panel
+    channel_panel_open: bool,
+
+    /// Channel values of the frame last shown in the channel panel, along with that frame
+    channel_stats: Option<(Arc<simtricks::Frame>, [ChannelStats; 3])>,
+
+    /// If true, show the most recent logs made by the first controlled plugin
+    log_panel_open: bool,
+
+    /// Frames recorded from the first controlled plugin, if recording
+    recording: Option<Recording>,
+
+    /// Frame count of the plugin when its last frame was recorded, to record each frame only once
+    recorded_frame_count: u64,
+
+    /// A brief message to show the user, and when it was shown
+    toast: Option<(String, Instant)>,
+
+    /// If true, the window title should be updated to reflect the current plugins
+    update_window_title: bool,
+
+    /// The display settings when the simulator started, to reset to from the Display menu
+    launch_display_settings: DisplaySettings,
+}
+
+/// Utility functions
+impl Simulator {
+    /// Create a new simulator for one or more plugins
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - Where to load the plugins to simulate from
+    /// * `matrix_config` - Configuration of the matrix, including its dimensions and the target FPS
+    /// * `allowed_hosts` - Hosts to allow the plugin to communicate with
+    /// * `path_maps` - Local paths to map to the plugin filesystem, and whether the plugin may change them.
+    /// * `history_size` - Maximum number of frames to keep in the history
+    /// * `warmup_frames` - Number of frames to generate and discard when the plugin starts
+    pub(crate) fn new(
+        sources: Vec<PluginSource>,
+        matrix_config: MatrixConfiguration,
+        allowed_hosts: Vec<String>,
+        path_maps: Vec<PathMap>,
+        history_size: usize,
+        warmup_frames: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if sources.is_empty() {
+            return Err("At least one plugin is required.".into());
+        }
+
+        // Warn if the history could use a lot of memory
+        let history_bytes =
+            sources.len() * history_size * matrix_config.width * matrix_config.height * 4;
+        if history_bytes > HISTORY_MEMORY_WARNING_BYTES {
+            log::warn!(
+                "Frame history may use up to {} MB of memory. Consider reducing the history size.",
+                history_bytes / (1024 * 1024)
+            );
+        }
+
+        let mut simulator = Self {
+            plugins: sources
+                .into_iter()
+                .map(|source| PluginInstance::new(source, &matrix_config, history_size))
+                .collect(),
+            view: PluginView::default(),
+            selected_plugin: 0,
+            diff_threshold: 0,
+            diff_overlay: false,
+            allowed_hosts,
+            new_allowed_host: String::new(),
+            path_maps,
+            vars: vec![],
+            history_size,
+            warmup_frames,
+            pending_dimensions: matrix_config.dimensions(),
+            requested_dimensions: RequestedDimensions {
+                width: Some(matrix_config.width),
+                height: Some(matrix_config.height),
+            },
+            fps: matrix_config.target_fps,
+            matrix_config,
+            display_transform: DisplayTransform::default(),
+            frame_transform: DisplayTransform::default().chain(),
+            gamma_enabled: false,
+            gamma: DEFAULT_GAMMA,
+            lut: None,
+            lut_enabled: false,
+            plugin_log_file: None,
+            test_pattern_color: [255, 255, 255, 255],
+            led_aspect: (1.0, 1.0),
+            channel_order: DEFAULT_CHANNEL_ORDER,
+            led_rounding: 0.0,
+            bloom_enabled: false,
+            dirty_tracking: true,
+            alpha_mode: AlphaMode::default(),
+            break_on_log: Arc::new(AtomicBool::new(false)),
+            confirm_restart: true,
+            restart_dialog_open: false,
+            strict_setup: false,
+            strict_dimensions: false,
+            clear_on_restart: true,
+            frame_server: None,
+            clipboard: None,
+            smoothing_frames: 1,
+            respect_plugin_fps: false,
+            pause_on_error: false,
+            continuous_repaint: false,
+            matrix_area: Rect::NOTHING,
+            snap_led_pixels: DEFAULT_SNAP_LED_PIXELS,
+            snap_requested: false,
+            snapshot_key: Key::S,
+            capture_every: None,
+            capture_dir: PathBuf::from("captures"),
+            open_dialog: None,
+            quit_requested: false,
+            record_toggle_requested: false,
+            frame_copy_requested: false,
+            fullscreen: false,
+            window_fullscreen: false,
+            blend_background: false,
+            background: [0, 0, 0],
+            step_size: 1,
+            latency_panel_open: false,
+            color_panel_open: false,
+            color_stats: None,
+            channel_panel_open: false,
+            channel_stats: None,
+            log_panel_open: false,
+            recording: None,
+            recorded_frame_count: 0,
+            toast: None,
+            update_window_title: false,
+            launch_display_settings: DisplaySettings::default(),
+        };
+
+        // Reset to the default display settings, unless the builders change them
+        simulator.launch_display_settings = simulator.display_settings_now();
+        Ok(simulator)
+    }
+
+    /// Remember the current display settings as the ones to reset to from the Display menu
+    ///
+    /// Call this once every other builder has been applied, so that their settings are the ones reset to.
+    pub(crate) fn with_launch_display_settings(mut self) -> Self {
+        self.launch_display_settings = self.display_settings_now();
+        self
+    }
+
+    /// Set the transform applied to the matrix when it is displayed
+    pub(crate) fn with_display_transform(mut self, display_transform: DisplayTransform) -> Self {
+        self.display_transform = display_transform;
+        self
+    }
+
+    /// Set the aspect ratio of a single LED (width, then height)
+    pub(crate) fn with_led_aspect(mut self, led_aspect: (u32, u32)) -> Self {
+        let smaller = led_aspect.0.min(led_aspect.1) as f32;
+        self.led_aspect = (led_aspect.0 as f32 / smaller, led_aspect.1 as f32 / smaller);
+        self
+    }
+
+    /// Set the index of the red, green, blue, and alpha channels within each LED of a frame
+    pub(crate) fn with_channel_order(mut self, channel_order: ChannelOrder) -> Self {
+        self.channel_order = channel_order;
+        self
+    }
+
+    /// Set how the alpha channel of each LED is displayed
+    pub(crate) fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Set whether to stop a plugin whose setup fails, rather than running it anyway
+    pub(crate) fn with_strict_setup(mut self, strict_setup: bool) -> Self {
+        self.strict_setup = strict_setup;
+        self
+    }
+
+    /// Set whether to stop a plugin that returns a frame that doesn't fit the matrix, rather than padding or trimming it
+    pub(crate) fn with_strict_dimensions(mut self, strict_dimensions: bool) -> Self {
+        self.strict_dimensions = strict_dimensions;
+        self
+    }
+
+    /// Set how several plugins are shown
+    pub(crate) fn with_view(mut self, view: PluginView) -> Self {
+        if view == PluginView::Diff && self.plugins.len() != 2 {
+            log::warn!("The diff view needs exactly two plugins. Showing the plugins side by side instead.");
+            return self;
+        }
+        self.view = view;
+        self
+    }
+
+    /// Set the largest difference between the channels of two LEDs for them to be considered matching in the diff
+    /// view
+    pub(crate) fn with_diff_threshold(mut self, diff_threshold: u8) -> Self {
+        self.diff_threshold = diff_threshold;
+        self
+    }
+
+    /// Set whether the diff view shows only the difference between the plugins, channel by channel
+    pub(crate) fn with_diff_overlay(mut self, diff_overlay: bool) -> Self {
+        self.diff_overlay = diff_overlay;
+        self
+    }
+
+    /// Set whether to ask the user to confirm before restarting a running plugin
+    pub(crate) fn with_restart_confirmation(mut self, confirm_restart: bool) -> Self {
+        self.confirm_restart = confirm_restart;
+        self
+    }
+
+    /// Set the dimensions of the matrix given by the user, taking any missing width or height from the plugin if it
+    /// declares its dimensions
+    pub(crate) fn with_requested_dimensions(
+        mut self,
+        requested_dimensions: RequestedDimensions,
+    ) -> Self {
+        self.requested_dimensions = requested_dimensions;
+        self
+    }
+
+    /// Set whether to blank the matrix on restart, rather than leaving the last frame in place
+    pub(crate) fn with_clear_on_restart(mut self, clear_on_restart: bool) -> Self {
+        self.clear_on_restart = clear_on_restart;
+        self
+    }
+
+    /// Set the number of recent frames to average together before showing a frame
+    pub(crate) fn with_smoothing(mut self, smoothing_frames: usize) -> Self {
+        self.smoothing_frames = smoothing_frames.max(1);
+        self
+    }
+
+    /// Set whether to run each plugin at the frame rate it requests, rather than the target FPS of the matrix
+    pub(crate) fn with_plugin_fps(mut self, respect_plugin_fps: bool) -> Self {
+        self.respect_plugin_fps = respect_plugin_fps;
+        self
+    }
+
+    /// Set whether to pause a plugin when an update fails, rather than stopping it
+    pub(crate) fn with_pause_on_error(mut self, pause_on_error: bool) -> Self {
+        self.pause_on_error = pause_on_error;
+        self
+    }
+
+    /// Set whether to draw plain LEDs from a cached texture, uploading only the LEDs that changed
+    pub(crate) fn with_dirty_tracking(mut self, dirty_tracking: bool) -> Self {
+        self.dirty_tracking = dirty_tracking;
+        self
+    }
+
+    /// Set the key that saves the state of the plugin to a file
+    pub(crate) fn with_snapshot_key(mut self, snapshot_key: Key) -> Self {
+        self.snapshot_key = snapshot_key;
+        self
+    }
+
+    /// Set how often to save the frames of each plugin as PNG images, and the directory to save them in
+    pub(crate) fn with_capture(mut self, capture_every: Option<u64>, capture_dir: PathBuf) -> Self {
+        self.capture_every = capture_every;
+        self.capture_dir = capture_dir;
+        self
+    }
+
+    /// Set the color of the surface to blend each LED over, or `None` to draw the LEDs over the window
+    pub(crate) fn with_background(mut self, background: Option<[u8; 3]>) -> Self {
+        self.blend_background = background.is_some();
+        self.background = background.unwrap_or(self.background);
+        self
+    }
+
+    /// Set the lookup tables that remap the displayed red, green, and blue values, or `None` to show colors unchanged
+    pub(crate) fn with_lut(mut self, lut: Option<ColorLut>) -> Self {
+        self.lut_enabled = lut.is_some();
+        self.lut = lut;
+        self
+    }
+
+    /// Set the extra variables to give the plugins, as keys and values
+    pub(crate) fn with_plugin_vars(mut self, vars: Vec<(String, String)>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Set the file to append each log made by the plugins to, or `None` to not save the logs
+    pub(crate) fn with_plugin_log_file(mut self, plugin_log_file: Option<PluginLogFile>) -> Self {
+        self.plugin_log_file = plugin_log_file;
+        self
+    }
+
+    /// Set whether to redraw the window continuously, rather than only when a new frame is installed
+    pub(crate) fn with_continuous_repaint(mut self, continuous_repaint: bool) -> Self {
+        self.continuous_repaint = continuous_repaint;
+        self
+    }
+
+    /// Start streaming the frames of the first plugin to WebSocket clients on the given local port
+    pub(crate) fn stream_frames(&mut self, port: u16) -> Result<(), Box<dyn Error>> {
+        self.frame_server = Some(FrameServer::start(
+            port,
+            Arc::clone(&self.plugins[0].frame),
+        )?);
+        Ok(())
+    }
+
+    /// Create new plugin threads for every plugin waiting to restart, freezing any plugin that fails to load
+    fn spawn_threads(&mut self, ctx: &Context) {
+        // Compose the display transform
+        self.frame_transform = self.display_transform.chain();
+
+        // Tell restarted plugins about any change to the frame rate
+        self.matrix_config.target_fps = self.fps;
+
+        for index in 0..self.plugins.len() {
+            if !self.plugins[index].restart_pending {
+                continue;
+            }
+            self.plugins[index].restart_pending = false;
+
+            let new_state = match self.spawn_thread(index, ctx) {
+                Ok(_) => PluginState::Running,
+                Err(e) => {
+                    log::error!(
+                        "Failed to create a new plugin thread for {}.",
+                        self.plugins[index].source
+                    );
+                    log::error!("{e}");
+                    PluginState::Frozen
+                }
+            };
+            self.plugins[index].set_state(new_state);
+        }
+        self.update_window_title = true;
+    }
+
+    fn spawn_thread(&mut self, index: usize, ctx: &Context) -> Result<(), Box<dyn Error>> {
+        log::info!("Spawning a new plugin thread.");
+
+        // Create the plugin, with a log breakpoint of its own
+        let log_breakpoint = LogBreakpoint {
+            enabled: Arc::clone(&self.break_on_log),
+            triggered: Arc::default(),
+        };
+        let options = PluginOptions {
+            source: self.plugins[index].source.clone(),
+            matrix_config: self.matrix_config.clone(),
+            allowed_hosts: self.allowed_hosts.clone(),
+            path_maps: self.path_maps.clone(),
+            vars: self.vars.clone(),
+            log_breakpoint: Some(log_breakpoint.clone()),
+            log_buffer: Some(Arc::clone(&self.plugins[index].logs)),
+            log_file: self.plugin_log_file.clone(),
+            requested_fps: Some(Arc::clone(&self.plugins[index].requested_fps)),
+        };
+        self.plugins[index].allowed_hosts = options.allowed_hosts.clone();
+        let frame_source: Box<dyn FrameSource + Send> = match options.source {
+            PluginSource::TestPattern(pattern) => {
+                self.plugins[index].metadata = PluginMetadata::default();
+                Box::new(PatternPlayer {
+                    pattern,
+                    dimensions: self.matrix_config.dimensions(),
+                    color: self.test_pattern_color,
+                    channel_order: self.channel_order,
+                    step: 0,
+                })
+            }
+            PluginSource::Frames(ref path) => {
+                self.plugins[index].metadata = PluginMetadata::default();
+                let player = FramePlayer::new(load_frames(path)?);
+
+                // Size the matrix to fit the frames, as for a plugin that declares its dimensions
+                if !self.requested_dimensions.is_complete() && index == 0 {
+                    if let Some(declared) = player.dimensions() {
+                        let (width, height) = self.requested_dimensions.fill(declared);
+                        self.adopt_dimensions(width, height, "dumped frames");
+                    }
+                }
+                Box::new(player)
+            }
+            _ => Box::new(self.load_plugin(index, &options)?),
+        };
+
+        // Keep the captures of each plugin apart, when comparing plugins
+        let capture_dir = if self.plugins.len() > 1 {
+            self.capture_dir.join(format!("plugin_{}", index + 1))
+        } else {
+            self.capture_dir.clone()
+        };
+
+        let instance = &mut self.plugins[index];
+
+        // Setup and spawn the plugin thread
+        {
+            let (commands, events) = instance.connect();
+            let state = PluginThreadState {
+                commands,
+                events,
+                requested_fps: Arc::clone(&instance.requested_fps),
+                latency: Arc::clone(&instance.latency),
+                log_breakpoint: Some(log_breakpoint),
+                on_new_frame: (!self.continuous_repaint).then(|| {
+                    let ctx = ctx.clone();
+                    Box::new(move || ctx.request_repaint()) as Box<dyn Fn() + Send>
+                }),
+            };
+            let config = PluginThreadConfig {
+                matrix_config: self.matrix_config.clone(),
+                fps: self.fps,
+                warmup_frames: self.warmup_frames,
+                smoothing_frames: self.smoothing_frames,
+                respect_plugin_fps: self.respect_plugin_fps,
+                pause_on_error: self.pause_on_error,
+                capture: self.capture_every.map(|every| FrameCapture {
+                    every,
+                    dir: capture_dir,
+                    channel_order: self.channel_order,
+                }),
+                strict_setup: self.strict_setup,
+                strict_dimensions: self.strict_dimensions,
+            };
+            thread::spawn(move || plugin_thread(frame_source, config, state));
+        }
+
+        // Show the first frame straight away, and carry on playing if the plugin was playing before
+        instance.send(PluginCommand::Step(1));
+        if instance.is_autoplaying() {
+            instance.send(PluginCommand::SetAutoplay(true));
+        }
+
+        Ok(())
+    }
+
+    /// Load the plugin for a new plugin thread, sizing the matrix as it prefers and reading its metadata
+    fn load_plugin(
+        &mut self,
+        index: usize,
+        options: &PluginOptions,
+    ) -> Result<Plugin<'static>, Box<dyn Error>> {
+        let mut plugin = create_plugin(options)?;
+
+        // Size the matrix as the plugin prefers, before it is set up. When comparing plugins, the first plugin decides.
+        if !self.requested_dimensions.is_complete() && index == 0 {
+            if let Some(declared) = preferred_dimensions(&mut plugin) {
+                let (width, height) = self.requested_dimensions.fill(declared);
+                if self.adopt_dimensions(width, height, "plugin") {
+                    plugin.set_config(&self.matrix_config.to_plugin_config())?;
+                }
+            }
+        }
+
+        Ok(plugin)
+    }
+
+    /// Resize the matrix to the dimensions declared by a plugin or its frames, blanking every plugin's frame
+    ///
+    /// Returns true if the matrix was resized, or false if it already had these dimensions.
+    fn adopt_dimensions(&mut self, width: usize, height: usize, declared_by: &str) -> bool {
+        if (width, height) == self.matrix_config.dimensions() || width == 0 || height == 0 {
+            return false;
+        }
+
+        log::info!("Using the {width}x{height} matrix declared by the {declared_by}.");
+        self.matrix_config.width = width;
+        self.matrix_config.height = height;
+        self.pending_dimensions = self.matrix_config.dimensions();
+        for plugin in &mut self.plugins {
+            plugin.clear_frame(&self.matrix_config);
+        }
+        true
+    }
+
+    /// Get the title of the window, including the names of the plugins if they provided them
+    fn window_title(&self) -> String {
+        let descriptions: Vec<String> = self
+            .plugins
+            .iter()
+            .filter_map(|plugin| plugin.metadata.describe())
+            .collect();
+        if descriptions.is_empty() {
+            String::from("Simtricks")
+        } else {
+            format!("Simtricks — {}", descriptions.join(" vs "))
+        }
+    }
+
+    /// Briefly show a message to the user
+    fn show_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), Instant::now()));
+    }
+
+    /// Get the plugins that the controls apply to: the selected plugin in the tab view, or every plugin otherwise
+    fn controlled_plugins(&self) -> &[PluginInstance] {
+        match self.view {
+            PluginView::SideBySide | PluginView::Diff => &self.plugins,
+            PluginView::Tabs => std::slice::from_ref(&self.plugins[self.selected_plugin]),
+        }
+    }
+
+    /// Get the plugins that the controls apply to, mutably
+    fn controlled_plugins_mut(&mut self) -> &mut [PluginInstance] {
+        match self.view {
+            PluginView::SideBySide | PluginView::Diff => &mut self.plugins,
+            PluginView::Tabs => std::slice::from_mut(&mut self.plugins[self.selected_plugin]),
+        }
+    }
+
+    /// Returns true if any of the controlled plugins are running
+    fn is_running(&self) -> bool {
+        self.controlled_plugins()
+            .iter()
+            .any(|plugin| plugin.state().is_live())
+    }
+
+    /// Returns true if any of the controlled plugins are automatically generating new frames
+    fn is_autoplaying(&self) -> bool {
+        self.controlled_plugins()
+            .iter()
+            .any(|plugin| plugin.is_autoplaying())
+    }
+
+    /// Get the number of frames back in the history that the controlled plugins are showing
+    fn history_offset(&self) -> usize {
+        self.controlled_plugins()
+            .iter()
+            .map(|plugin| plugin.history_offset)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get the number of frames in the history that every controlled plugin can step back through
+    fn history_length(&self) -> usize {
+        self.controlled_plugins()
+            .iter()
+            .map(|plugin| plugin.history_length())
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Keep plugins shown side by side in lockstep, by pausing every plugin if any of them was paused (i.e. by a log
+    /// breakpoint)
+    fn keep_in_lockstep(&mut self) {
+        if self.view == PluginView::Tabs {
+            return;
+        }
+
+        if self.plugins.iter().any(|plugin| plugin.is_autoplaying())
+            && self.plugins.iter().any(|plugin| !plugin.is_autoplaying())
+        {
+            for plugin in &mut self.plugins {
+                plugin.set_autoplay(false);
+            }
+        }
+    }
+}
+
+/// Control functions
+impl Simulator {
+    /// Play/pause the controlled plugins
+    fn toggle_autoplay(&mut self) {
+        let autoplay = !self.is_autoplaying();
+        for plugin in self.controlled_plugins_mut() {
+            // Return to the latest frame
+            plugin.history_offset = 0;
+
+            plugin.set_autoplay(autoplay);
+        }
+    }
+
+    /// Advance the given number of frames, generating every frame that isn't already in the history
+    fn step(&mut self, frames: usize) {
+        // If looking back through the history, move forward through the history first, leaving any frames beyond the
+        // latest frame to be generated
+        let frames_to_generate = frames.saturating_sub(self.history_offset());
+        for plugin in self.controlled_plugins_mut() {
+            plugin.history_offset = plugin.history_offset.saturating_sub(frames);
+        }
+        if frames_to_generate == 0 {
+            return;
+        }
+
+        // If every plugin is done, stay on the final frames
+        if self
+            .controlled_plugins()
+            .iter()
+            .all(|plugin| plugin.state() == PluginState::Finished)
+        {
+            self.show_toast("No more frames");
+            return;
+        }
+
+        // Tell the plugin update threads to generate each new frame in turn, so that every frame is seen by the plugin
+        for plugin in self.controlled_plugins() {
+            plugin.send(PluginCommand::Step(frames_to_generate));
+        }
+    }
+
+    /// Returns true if stepping forward is possible, either through the history or by generating a new frame
+    fn can_step(&self) -> bool {
+        self.controlled_plugins()
+            .iter()
+            .any(|plugin| plugin.state().is_live() || plugin.state() == PluginState::Finished)
+            || self.history_offset() > 0
+    }
+
+    /// Returns true if there is an older frame in the history to step back to
+    fn can_step_back(&self) -> bool {
+        self.history_offset() + 1 < self.history_length()
+    }
+
+    /// Go to the previous frame in the history
+    fn step_back(&mut self) {
+        if self.can_step_back() {
+            for plugin in self.controlled_plugins_mut() {
+                plugin.history_offset += 1;
+            }
+        }
+    }
+
+    /// Kill the threads of the controlled plugins and create new ones
+    fn restart(&mut self) {
+        let matrix_config = self.matrix_config.clone();
+        let clear_on_restart = self.clear_on_restart;
+        for plugin in self.controlled_plugins_mut() {
+            plugin.restart(&matrix_config, clear_on_restart);
+        }
+    }
+
+    /// Change the dimensions of the matrix, and restart the plugin so it is set up with the new dimensions
+    fn resize_matrix(&mut self, new_width: usize, new_height: usize) {
+        if new_width < 1 || new_height < 1 {
+            log::warn!("Matrix dimensions must be at least 1x1.");
+            return;
+        }
+
+        log::info!("Resizing matrix to {new_width}x{new_height}.");
+        self.requested_dimensions = RequestedDimensions {
+            width: Some(new_width),
+            height: Some(new_height),
+        };
+        self.matrix_config.width = new_width;
+        self.matrix_config.height = new_height;
+        self.pending_dimensions = self.matrix_config.dimensions();
+
+        // Restart every plugin so it is set up with the new dimensions, reallocating the frames as the last frames no
+        // longer fit the matrix
+        for plugin in &mut self.plugins {
+            plugin.restart(&self.matrix_config, true);
+        }
+    }
+
+    /// Restart the controlled plugins, asking the user to confirm first if any of them are running
+    fn request_restart(&mut self) {
+        if self.confirm_restart && self.is_running() {
+            self.restart_dialog_open = true;
+        } else {
+            self.restart();
+        }
+    }
+
+    /// Copy the frame currently shown for the first controlled plugin to the clipboard, as an image
+    fn copy_frame_to_clipboard(&mut self) {
+        let frame = (self.frame_transform)(&self.controlled_plugins()[0].displayed_frame());
+        let image = rasterize_frame(&frame, self.channel_order, CLIPBOARD_LED_SIZE);
+        let result = match &mut self.clipboard {
+            Some(clipboard) => Ok(clipboard),
+            None => Clipboard::new().map(|clipboard| self.clipboard.insert(clipboard)),
+        }
+        .and_then(|clipboard| {
+            clipboard.set_image(ImageData {
+                width: image.width,
+                height: image.height,
+                bytes: Cow::Owned(image.pixels),
+            })
+        });
+        match result {
+            Ok(_) => self.show_toast("Copied frame to clipboard"),
+            Err(e) => {
+                log::error!("Failed to copy frame to clipboard.");
+                log::debug!("Clipboard failed with the following error: {e}");
+                self.show_toast("Failed to copy frame to clipboard");
+            }
+        }
+    }
+
+    /// Copy the most common colors of the frame currently shown for the first controlled plugin to the clipboard, as a
+    /// JSON array of hex strings
+    fn copy_palette(&mut self, ctx: &Context) {
+        let frame = self.controlled_plugins()[0].displayed_frame();
+        let colors = palette(&frame, self.channel_order, PALETTE_SIZE);
+        let color_count = colors.len();
+        ctx.output_mut(|output| output.copied_text = serde_json::json!(colors).to_string());
+        self.show_toast(format!("Copied {color_count} colors to clipboard"));
+    }
+
+    /// Copy the frame currently shown for the first controlled plugin to the clipboard, as a Rust `const` declaration
+    ///
+    /// The frame is copied as the plugin produced it, without any display transforms.
+    fn copy_frame_as_rust(&mut self, ctx: &Context) {
+        let frame = self.controlled_plugins()[0].displayed_frame();
+        ctx.output_mut(|output| output.copied_text = frame_to_rust(&frame));
+        self.show_toast("Copied frame as Rust");
+    }
+
+    /// Get the frame rate at which the plugins generate frames during autoplay
+    fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Change the frame rate at which the plugins generate frames during autoplay, without restarting them
+    fn set_fps(&mut self, fps: f32) {
+        self.fps = fps.clamp(1.0, MAX_FPS);
+        for plugin in &self.plugins {
+            plugin.send(PluginCommand::SetFps(self.fps));
+        }
+    }
+
+    /// Raise or lower the frame rate by the given amount, letting the user know the new frame rate
+    fn nudge_fps(&mut self, change: f32) {
+        self.set_fps(self.fps + change);
+        self.show_toast(format!("FPS: {}", self.fps));
+    }
+
+    /// Ask the first controlled plugin to save its state to, or restore its state from, the snapshot file
+    fn request_state(&mut self, command: PluginCommand) {
+        let message = match &command {
+            PluginCommand::SaveState(path) => format!("Saving plugin state to {}", path.display()),
+            PluginCommand::LoadState(path) => {
+                format!("Restoring plugin state from {}", path.display())
+            }
+            _ => return,
+        };
+        self.controlled_plugins()[0].send(command);
+        self.show_toast(message);
+    }
+
+    /// Start recording the first controlled plugin, or stop recording and ask where to save the recording as a GIF
+    fn toggle_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            log::info!("Started recording.");
+            self.recording = Some(Recording::new());
+            self.recorded_frame_count = 0;
+            self.show_toast("Recording started");
+            return;
+        };
+
+        if recording.is_empty() {
+            self.show_toast("Nothing was recorded");
+            return;
+        }
+
+        // Ask where to save the recording, discarding it if the user cancels
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Recording")
+            .add_filter("GIF", &["gif"])
+            .set_file_name("recording.gif")
+            .save_file()
+        else {
+            log::info!("Discarded recording of {} frames.", recording.len());
+            self.show_toast("Recording discarded");
+            return;
+        };
+
+        match recording.save_gif(self.channel_order, RECORDING_LED_SIZE, &path) {
+            Ok(_) => {
+                log::info!(
+                    "Saved recording of {} frames to {}.",
+                    recording.len(),
+                    path.display()
+                );
+                self.show_toast(format!("Saved recording to {}", path.display()));
+            }
+            Err(e) => {
+                log::error!("Failed to save recording to {}.", path.display());
+                log::debug!("Saving recording failed with the following error: {e}");
+                self.show_toast("Failed to save recording");
+            }
+        }
+    }
+
+    /// Add the frame shown for the first controlled plugin to the recording, if recording and the plugin has installed
+    /// a new frame since the last one was recorded
+    fn record_frame(&mut self) {
+        if self.recording.is_none() {
+            return;
+        }
+
+        let plugin = &self.controlled_plugins()[0];
+        let frame_count = plugin.frame_count;
+        if frame_count == self.recorded_frame_count {
+            return;
+        }
+        let frame = (self.frame_transform)(&plugin.displayed_frame());
+
+        self.recorded_frame_count = frame_count;
+        if let Some(recording) = &mut self.recording {
+            recording.push(frame);
+        }
+    }
+
+    /// Replace the first controlled plugin with the given plugin, and start it on a blank matrix
+    ///
+    /// The matrix keeps its dimensions, unless the new plugin declares its own and they weren't given by the user.
+    fn open_plugin(&mut self, source: PluginSource) {
+        log::info!("Opening plugin {source}.");
+        let matrix_config = self.matrix_config.clone();
+        let plugin = &mut self.controlled_plugins_mut()[0];
+        plugin.source = source;
+        plugin.replaced_source = None;
+        plugin.metadata = PluginMetadata::default();
+        plugin.restart(&matrix_config, true);
+    }
+
+    /// Get the test pattern played by the first controlled plugin, if it is playing one
+    fn test_pattern(&self) -> Option<TestPattern> {
+        match self.controlled_plugins()[0].source {
+            PluginSource::TestPattern(test_pattern) => Some(test_pattern),
+            _ => None,
+        }
+    }
+
+    /// Play the given test pattern in place of the first controlled plugin, or go back to the plugin if `None`
+    fn show_test_pattern(&mut self, test_pattern: Option<TestPattern>) {
+        let matrix_config = self.matrix_config.clone();
+        let plugin = &mut self.controlled_plugins_mut()[0];
+        let source = match test_pattern {
+            Some(test_pattern) => {
+                // Remember the plugin to go back to, unless another pattern already took its place
+                if !matches!(plugin.source, PluginSource::TestPattern(_)) {
+                    plugin.replaced_source = Some(plugin.source.clone());
+                }
+                PluginSource::TestPattern(test_pattern)
+            }
+            None => match plugin.replaced_source.take() {
+                Some(source) => source,
+                None => return,
+            },
+        };
+
+        log::info!("Opening plugin {source}.");
+        plugin.source = source;
+        plugin.metadata = PluginMetadata::default();
+        plugin.restart(&matrix_config, true);
+    }
+
+    /// Restart every plugin playing a test pattern, so that it picks up the current test pattern color
+    fn restart_test_patterns(&mut self) {
+        for plugin in &mut self.plugins {
+            if matches!(plugin.source, PluginSource::TestPattern(_)) {
+                plugin.restart(&self.matrix_config, false);
+            }
+        }
+    }
+
+    /// Ask the user to pick a plugin file, and open it if they pick one
+    fn pick_plugin(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .set_title("Open Plugin")
+            .add_filter("Plugin", &PLUGIN_EXTENSIONS)
+            .pick_file()
+        {
+            self.open_plugin(PluginSource::File(path));
+        }
+    }
+
+    /// Add another plugin to simulate, showing it in a tab of its own
+    fn add_plugin(&mut self, source: PluginSource) {
+        log::info!("Adding plugin {source}.");
+        self.plugins.push(PluginInstance::new(
+            source,
+            &self.matrix_config,
+            self.history_size,
+        ));
+
+        // Select the new plugin, so the controls apply only to it
+        self.view = PluginView::Tabs;
+        self.selected_plugin = self.plugins.len() - 1;
+    }
+
+    /// Ask the user to pick a plugin file, and add it alongside the current plugins if they pick one
+    fn pick_added_plugin(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .set_title("Add Plugin")
+            .add_filter("Plugin", &PLUGIN_EXTENSIONS)
+            .pick_file()
+        {
+            self.add_plugin(PluginSource::File(path));
+        }
+    }
+
+    /// Open the first file dropped onto the window, if it is a WASM plugin
+    fn open_dropped_file(&mut self, ctx: &Context) {
+        let Some(path) = ctx.input(|input| {
+            input
+                .raw
+                .dropped_files
+                .first()
+                .and_then(|file| file.path.clone())
+        }) else {
+            return;
+        };
+
+        if is_plugin_file(&path) {
+            self.open_plugin(PluginSource::File(path));
+        } else {
+            log::warn!(
+                "Ignoring dropped file {}, which is not a .wasm or .wat plugin.",
+                path.display()
+            );
+            self.show_toast("Only .wasm and .wat plugins can be opened");
+        }
+    }
+
+    /// Stop every plugin thread, so that they exit cleanly before the window closes
+    fn stop_plugins(&mut self) {
+        for plugin in &mut self.plugins {
+            plugin.kill();
+        }
+    }
+
+    /// Handle any keyboard shortcuts
+    fn consume_shortcuts(&mut self, ctx: &Context) {
+        // Leave copying to any text field that has keyboard focus
+        let matrix_focused = ctx.memory(|memory| memory.focus().is_none());
+        let typing = ctx.wants_keyboard_input();
+
+        ctx.input_mut(|input_state| {
+            // If Ctrl+Q is pressed, quit
+            if input_state.consume_key(Modifiers::COMMAND, Key::Q) {
+                self.quit_requested = true;
+                return;
+            }
+
+            // While the open dialog is open, leave the keyboard to it, except for Escape to close it
+            if self.open_dialog.is_some() {
+                if input_state.consume_key(Modifiers::NONE, Key::Escape) {
+                    self.open_dialog = None;
+                }
+                return;
+            }
+
+            // While text is being typed elsewhere, i.e. a new allowed host, leave the keyboard to it
+            if typing {
+                return;
+            }
+
+            // While the restart confirmation dialog is open, only Enter and Escape do anything
+            if self.restart_dialog_open {
+                if input_state.consume_key(Modifiers::NONE, Key::Enter) {
+                    self.restart_dialog_open = false;
+                    self.restart();
+                } else if input_state.consume_key(Modifiers::NONE, Key::Escape) {
+                    self.restart_dialog_open = false;
+                }
+                return;
+            }
+
+            // If F11 is pressed, toggle fullscreen. If Escape is pressed, leave fullscreen.
+            if input_state.consume_key(Modifiers::NONE, Key::F11) {
+                self.fullscreen = !self.fullscreen;
+            } else if self.fullscreen && input_state.consume_key(Modifiers::NONE, Key::Escape) {
+                self.fullscreen = false;
+            }
+
+            // If space is pressed, toggle autoplay
+            if input_state.consume_key(Modifiers::NONE, Key::Space) {
+                self.toggle_autoplay();
+            }
+
+            // If 'N' or right arrow is pressed and autoplay is off, step forward by the step size. If Shift+Right is
+            // pressed, jump further. Every press is counted, so presses made between two redraws aren't merged.
+            let steps = input_state.count_and_consume_key(Modifiers::NONE, STEP_KEY)
+                + input_state.count_and_consume_key(Modifiers::NONE, Key::ArrowRight);
+            let jumps = input_state.count_and_consume_key(Modifiers::SHIFT, Key::ArrowRight);
+            if !self.is_autoplaying() {
+                let frames = steps * self.step_size + jumps * JUMP_STEP_SIZE;
+                if frames > 0 {
+                    self.step(frames);
+                }
+            }
+
+            // If 'B' or left arrow is pressed and autoplay is off, step back to the previous frame, once for each press
+            let steps_back = input_state.count_and_consume_key(Modifiers::NONE, STEP_BACK_KEY)
+                + input_state.count_and_consume_key(Modifiers::NONE, Key::ArrowLeft);
+            if !self.is_autoplaying() {
+                for _ in 0..steps_back {
+                    self.step_back();
+                }
+            }
+
+            // If '[' or ']' is typed, lower or raise the frame rate
+            let typed = |text: &str| {
+                input_state
+                    .events
+                    .iter()
+                    .any(|event| matches!(event, egui::Event::Text(typed) if typed == text))
+            };
+            if typed("[") {
+                self.nudge_fps(-FPS_STEP);
+            } else if typed("]") {
+                self.nudge_fps(FPS_STEP);
+            }
+
+            // If the snapshot key (by default 'S') is pressed, save the state of the plugin. If 'L' is pressed, load it.
+            let snapshot_path = self.capture_dir.join(SNAPSHOT_FILE_NAME);
+            if input_state.consume_key(Modifiers::NONE, self.snapshot_key) {
+                self.request_state(PluginCommand::SaveState(snapshot_path));
+            } else if input_state.consume_key(Modifiers::NONE, LOAD_STATE_KEY) {
+                self.request_state(PluginCommand::LoadState(snapshot_path));
+            }
+
+            // If 'R' is pressed, restart the plugin
+            if input_state.consume_key(Modifiers::NONE, RESTART_KEY) {
+                self.request_restart()
+            }
+
+            // If 'H' is pressed, show or hide the update latency histogram
+            if input_state.consume_key(Modifiers::NONE, LATENCY_PANEL_KEY) {
+                self.latency_panel_open = !self.latency_panel_open;
+            }
+
+            // If 'O' is pressed, show or hide the output logged by the plugin
+            if input_state.consume_key(Modifiers::NONE, LOG_PANEL_KEY) {
+                self.log_panel_open = !self.log_panel_open;
+            }
+
+            // If 'P' is pressed, show or hide the colors of the frame
+            if input_state.consume_key(Modifiers::NONE, COLOR_PANEL_KEY) {
+                self.color_panel_open = !self.color_panel_open;
+            }
+
+            // If 'V' is pressed, show or hide the values of each channel of the frame
+            if input_state.consume_key(Modifiers::NONE, CHANNEL_PANEL_KEY) {
+                self.channel_panel_open = !self.channel_panel_open;
+            }
+
+            // If 'G' is pressed, start or stop recording a GIF
+            if input_state.consume_key(Modifiers::NONE, RECORD_KEY) {
+                self.record_toggle_requested = true;
+            }
+
+            // If 'A' is pressed, switch to the next alpha mode
+            if input_state.consume_key(Modifiers::NONE, ALPHA_MODE_KEY) {
+                self.alpha_mode = self.alpha_mode.next();
+                self.show_toast(format!("Alpha mode: {}", self.alpha_mode.name()));
+            }
+
+            // If Ctrl+C is pressed over the matrix, copy the current frame to the clipboard
+            if matrix_focused && input_state.consume_key(Modifiers::COMMAND, Key::C) {
+                self.frame_copy_requested = true;
+            }
+        });
+
+        // Copy the frame now that the input is no longer locked, so the clipboard isn't accessed while it is
+        if self.frame_copy_requested {
+            self.frame_copy_requested = false;
+            self.copy_frame_to_clipboard();
+        }
+
+        // Start or stop recording now that the input is no longer locked, as choosing where to save may open a dialog
+        if self.record_toggle_requested {
+            self.record_toggle_requested = false;
+            self.toggle_recording();
+        }
+    }
+}
+
+/// GUI functions
+impl Simulator {
+    /// Get the hover text of the restart button, including any metadata provided by the controlled plugins
+    fn restart_hover_text(&self) -> String {
+        let mut text = String::from("Restart plugin (R)");
+        let plugins = self.controlled_plugins();
+        for plugin in plugins {
+            // Separate the metadata of each plugin when comparing several plugins
+            if plugins.len() > 1 {
+                text.push_str(&format!("\n\n{}", plugin.source));
+            }
+            for (label, value) in [
+                ("Name", &plugin.metadata.name),
+                ("Version", &plugin.metadata.version),
+                ("Author", &plugin.metadata.author),
+            ] {
+                if let Some(value) = value {
+                    text.push_str(&format!("\n{label}: {value}"));
+                }
+            }
+        }
+        text
+    }
+
+    /// Draw the matrix, or the plugins and the difference between them in the diff view
+    ///
+    /// # Arguments
+    ///
+    /// * `difference` - Where the two plugins differ, in the diff view
+    fn matrix(&mut self, ctx: &Context, difference: Option<&Difference>) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Allocate our painter
+            let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click());
+            self.matrix_area = response.rect;
+
+            // Note where the matrix was clicked, to copy the color of the LED there
+            let clicked = response
+                .clicked()
+                .then(|| response.interact_pointer_pos())
+                .flatten();
+
+            // In the diff overlay, show only the difference between the two plugins, as large as possible
+            if let (Some(difference), true) = (difference, self.diff_overlay) {
+                let cells = self.labelled_grid(
+                    &painter,
+                    response.rect,
+                    &[format!(
+                        "{} vs {} (differences ×{DIFF_OVERLAY_SCALE})",
+                        self.plugins[0].label(),
+                        self.plugins[1].label()
+                    )],
+                );
+                let difference = &difference.frame;
+                self.draw_frame(&painter, cells[0], Id::new("difference"), difference);
+                if let Some(pos) = clicked {
+                    self.copy_led_color(ctx, cells[0], difference, pos);
+                }
+                return;
+            }
+
+            // In the diff view, show the two plugins and the difference between them
+            if let Some(difference) = difference {
+                let cells = self.labelled_grid(
+                    &painter,
+                    response.rect,
+                    &[
+                        self.plugins[0].label(),
+                        self.plugins[1].label(),
+                        String::from("Difference"),
+                    ],
+                );
+                self.plugin_matrix(&painter, cells[0], &self.plugins[0]);
+                self.plugin_matrix(&painter, cells[1], &self.plugins[1]);
+                let difference = &difference.frame;
+                self.draw_frame(&painter, cells[2], Id::new("difference"), difference);
+                if let Some(pos) = clicked {
+                    match cells.iter().position(|cell| cell.contains(pos)) {
+                        Some(2) => self.copy_led_color(ctx, cells[2], difference, pos),
+                        Some(index) => self.copy_plugin_led_color(ctx, index, cells[index], pos),
+                        None => {}
+                    }
+                }
+                return;
+            }
+
+            // With a single plugin shown, use the whole painter
+            if self.controlled_plugins().len() == 1 {
+                self.plugin_matrix(&painter, response.rect, &self.controlled_plugins()[0]);
+                if let Some(pos) = clicked {
+                    let frame =
+                        (self.frame_transform)(&self.controlled_plugins()[0].displayed_frame());
+                    self.copy_led_color(ctx, response.rect, &frame, pos);
+                }
+                return;
+            }
+
+            // Otherwise, lay the plugins out in a grid
+            let labels: Vec<String> = self.plugins.iter().map(|plugin| plugin.label()).collect();
+            let cells = self.labelled_grid(&painter, response.rect, &labels);
+            for (plugin, cell) in self.plugins.iter().zip(&cells) {
+                self.plugin_matrix(&painter, *cell, plugin);
+            }
+            if let Some(pos) = clicked {
+                if let Some(index) = cells.iter().position(|cell| cell.contains(pos)) {
+                    self.copy_plugin_led_color(ctx, index, cells[index], pos);
+                }
+            }
+        });
+    }
+
+    /// Split a rectangle into a grid of labelled cells that is as close to square as possible, returning the area of
+    /// each cell below its label
+    fn labelled_grid(&self, painter: &Painter, rect: Rect, labels: &[String]) -> Vec<Rect> {
+        let columns = (labels.len() as f32).sqrt().ceil() as usize;
+        let rows = labels.len().div_ceil(columns);
+        let cell_size = Vec2::new(rect.width() / columns as f32, rect.height() / rows as f32);
+        labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| {
+                let cell = Rect::from_min_size(
+                    rect.min
+                        + Vec2::new(
+                            (index % columns) as f32 * cell_size.x,
+                            (index / columns) as f32 * cell_size.y,
+                        ),
+                    cell_size,
+                )
+                .shrink(PLUGIN_SPACING / 2.0);
+
+                // Label the cell above its matrix
+                let (label_rect, matrix_rect) =
+                    cell.split_top_bottom_at_y(cell.top() + PLUGIN_LABEL_HEIGHT);
+                painter.text(
+                    label_rect.center(),
+                    Align2::CENTER_CENTER,
+                    label,
+                    FontId::proportional(14.0),
+                    Color32::WHITE,
+                );
+                matrix_rect
+            })
+            .collect()
+    }
+
+    /// Work out where the two plugins differ, building a frame with matching LEDs black and differing LEDs lit in
+    /// proportion to how different they are
+    ///
+    /// In the overlay, each channel of a differing LED shows how different that channel is, scaled up so that small
+    /// differences stand out. Otherwise, differing LEDs are gray, as bright as their most different channel.
+    fn difference(&self) -> Difference {
+        let difference = channel_difference(
+            &(self.frame_transform)(&self.plugins[0].displayed_frame()),
+            &(self.frame_transform)(&self.plugins[1].displayed_frame()),
+        );
+
+        let [red, green, blue, alpha] = self.channel_order;
+        let mut differing_leds = 0;
+        let mut total = 0;
+        let frame = difference
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|delta| {
+                        total += delta.iter().map(|&channel| channel as u64).sum::<u64>();
+
+                        let largest = delta.iter().copied().max().unwrap_or(0);
+                        let mut led = if largest <= self.diff_threshold {
+                            [0; 4]
+                        } else if self.diff_overlay {
+                            differing_leds += 1;
+                            delta.map(|channel| channel.saturating_mul(DIFF_OVERLAY_SCALE))
+                        } else {
+                            differing_leds += 1;
+                            let mut led = [0; 4];
+                            led[red] = largest;
+                            led[green] = largest;
+                            led[blue] = largest;
+                            led
+                        };
+                        led[alpha] = 255;
+                        led
+                    })
+                    .collect()
+            })
+            .collect();
+        Difference {
+            frame,
+            differing_leds,
+            total,
+        }
+    }
+
+    /// Draw the matrix of a single plugin, centered within the given rectangle
+    fn plugin_matrix(&self, painter: &Painter, rect: Rect, plugin: &PluginInstance) {
+        // Grab the frame, from the history if stepping back through it, and transform it for display
+        let frame = (self.frame_transform)(&plugin.displayed_frame());
+        self.draw_frame(painter, rect, Id::new(Arc::as_ptr(&plugin.frame)), &frame);
+
+        // If the plugin has stopped and the latest frame is shown, dim the matrix and explain why
+        let message = match plugin.state() {
+            _ if plugin.history_offset > 0 => None,
+            PluginState::Running => None,
+            PluginState::Finished => Some("Plugin complete - press R to restart"),
+            PluginState::Errored => Some("Plugin stopped due to an error - press R to restart"),
+            PluginState::Paused => {
+                Some("Plugin paused due to an error - step to retry, or press R to restart")
+            }
+            PluginState::Frozen => Some("Failed to load plugin - press R to retry"),
+        };
+        if let Some(message) = message {
+            painter.rect_filled(rect, Rounding::ZERO, Color32::from_black_alpha(180));
+            painter.text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                message,
+                FontId::proportional(18.0),
+                Color32::WHITE,
+            );
+        }
+
+        // Show the last error the plugin thread ran into, even if the plugin kept running, or else the last problem it
+        // worked around
+        let badge = match (&plugin.error, &plugin.warning) {
+            (Some(error), _) => Some((error, Color32::DARK_RED)),
+            (None, Some(warning)) => Some((warning, WARNING_BADGE_COLOR)),
+            (None, None) => None,
+        };
+        if let Some((message, color)) = badge {
+            let galley = painter.layout(
+                message.clone(),
+                FontId::proportional(14.0),
+                Color32::WHITE,
+                rect.width() - 16.0,
+            );
+            let position = rect.left_top() + Vec2::splat(8.0);
+            let badge = Rect::from_min_size(position, galley.size()).expand(4.0);
+            painter.rect_filled(badge, Rounding::same(4.0), color);
+            painter.galley(position, galley);
+        }
+    }
+
+    /// Get the aspect ratio of a single LED as displayed, which turns with the matrix
+    fn displayed_led_aspect(&self) -> Vec2 {
+        if self.display_transform.rotation.is_sideways() {
+            Vec2::new(self.led_aspect.1, self.led_aspect.0)
+        } else {
+            Vec2::new(self.led_aspect.0, self.led_aspect.1)
+        }
+    }
+
+    /// Draw the LEDs of a matrix as a single texture, uploading only the part that changed since it was last drawn
+    ///
+    /// # Arguments
+    ///
+    /// * `painter` - Painter to draw with
+    /// * `id` - Name of the matrix, under which its texture is kept
+    /// * `grid` - Area to draw the matrix in
+    /// * `dimensions` - Dimensions of the matrix (width, then height)
+    /// * `colors` - Color of each LED, row by row
+    fn draw_texture(
+        &self,
+        painter: &Painter,
+        id: Id,
+        grid: Rect,
+        dimensions: (usize, usize),
+        colors: Vec<Color32>,
+    ) {
+        let ctx = painter.ctx();
+        let size = [dimensions.0, dimensions.1];
+        let cached: Option<MatrixTexture> = ctx.data(|data| data.get_temp(id));
+        let texture = match cached {
+            // If the matrix has kept its size, upload only the smallest rectangle holding every LED that changed
+            Some(mut cached) if cached.texture.size() == size => {
+                let changed = (0..colors.len())
+                    .filter(|&index| cached.colors[index] != colors[index])
+                    .map(|index| (index % dimensions.0, index / dimensions.0));
+                let bounds = changed.fold(None, |bounds, (x, y)| match bounds {
+                    None => Some((x, y, x, y)),
+                    Some((min_x, min_y, max_x, max_y)) => {
+                        Some((min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)))
+                    }
+                });
+                if let Some((min_x, min_y, max_x, max_y)) = bounds {
+                    let pixels = (min_y..=max_y)
+                        .flat_map(|y| &colors[y * dimensions.0 + min_x..=y * dimensions.0 + max_x])
+                        .copied()
+                        .collect();
+                    let image = ColorImage {
+                        size: [max_x - min_x + 1, max_y - min_y + 1],
+                        pixels,
+                    };
+                    cached
+                        .texture
+                        .set_partial([min_x, min_y], image, TextureOptions::NEAREST);
+                }
+                cached.texture
+            }
+
+            // Otherwise, create a new texture
+            _ => {
+                let image = ColorImage {
+                    size,
+                    pixels: colors.clone(),
+                };
+                ctx.load_texture(format!("matrix {id:?}"), image, TextureOptions::NEAREST)
+            }
+        };
+
+        painter.image(
+            texture.id(),
+            grid,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        ctx.data_mut(|data| data.insert_temp(id, MatrixTexture { texture, colors }));
+    }
+
+    /// Resize the window so that the matrix is drawn with each LED exactly `snap_led_pixels` pixels across
+    ///
+    /// Only a single matrix can be snapped, as the grid of several matrices includes labels and spacing.
+    fn snap_window(&mut self, frame: &mut Frame) {
+        if self.view == PluginView::Diff || self.controlled_plugins().len() != 1 {
+            self.show_toast("1:1 snap only works when a single matrix is shown");
+            return;
+        }
+
+        // Convert the LED size from physical pixels to points
+        let pixels_per_point = frame.info().native_pixels_per_point.unwrap_or(1.0);
+        let led_size =
+            self.displayed_led_aspect() * (self.snap_led_pixels as f32 / pixels_per_point);
+
+        // Grow or shrink the window by the difference between the current and snapped matrix sizes
+        let displayed_dimensions = self
+            .display_transform
+            .displayed_dimensions(self.matrix_config.dimensions());
+        let matrix_size = Vec2::new(
+            led_size.x * displayed_dimensions.0 as f32,
+            led_size.y * displayed_dimensions.1 as f32,
+        );
+        let window_size = frame.info().window_info.size - self.matrix_area.size() + matrix_size;
+        frame.set_window_size(window_size);
+        log::info!("Snapped window to {} pixels per LED.", self.snap_led_pixels);
+    }
+
+    /// Lay out the LEDs of the displayed matrix within the given rectangle, returning the area covered by the LEDs and
+    /// the size of each LED
+    fn led_grid(&self, rect: Rect) -> (Rect, Vec2) {
+        // Get the dimensions of the matrix as displayed, after rotation
+        let displayed_dimensions = self
+            .display_transform
+            .displayed_dimensions(self.matrix_config.dimensions());
+
+        // Get the aspect ratio of the LEDs as displayed, which turns with the matrix
+        let led_aspect = self.displayed_led_aspect();
+
+        // Calculate the LED sidelength for x and y based on the window size and number of pixels, and choose smallest value for LED sidelength
+        let sidelength = [
+            rect.width() / (displayed_dimensions.0 as f32 * led_aspect.x), // Sidelength from width
+            rect.height() / (displayed_dimensions.1 as f32 * led_aspect.y), // Sidelength from height
+        ]
+        .iter()
+        .min_by(|a, b| a.partial_cmp(b).unwrap()) // Pick smaller of the two
+        .copied()
+        .unwrap();
+        let led_size = led_aspect * sidelength;
+
+        // Center the grid within the rectangle, so that any empty space is split evenly
+        let grid_size = Vec2::new(
+            led_size.x * displayed_dimensions.0 as f32,
+            led_size.y * displayed_dimensions.1 as f32,
+        );
+        (Rect::from_center_size(rect.center(), grid_size), led_size)
+    }
+
+    /// Find the LED of the displayed matrix at a position within the given rectangle, as a column and row of the frame
+    /// as displayed
+    fn led_at(&self, rect: Rect, pos: Pos2) -> Option<(usize, usize)> {
+        let (grid, led_size) = self.led_grid(rect);
+        if !grid.contains(pos) {
+            return None;
+        }
+
+        // Clamp to the last LED, as a click on the far edge of the grid lies just outside it
+        let (width, height) = self
+            .display_transform
+            .displayed_dimensions(self.matrix_config.dimensions());
+        let offset = pos - grid.min;
+        Some((
+            ((offset.x / led_size.x) as usize).min(width - 1),
+            ((offset.y / led_size.y) as usize).min(height - 1),
+        ))
+    }
+
+    /// Copy the color of the LED at a position within the given rectangle to the clipboard, as a `#RRGGBB` hex string
+    ///
+    /// The frame is the one drawn in the rectangle, already transformed for display.
+    fn copy_led_color(&mut self, ctx: &Context, rect: Rect, frame: &[Vec<[u8; 4]>], pos: Pos2) {
+        let Some(led) = self
+            .led_at(rect, pos)
+            .and_then(|(x, y)| frame.get(y).and_then(|row| row.get(x)))
+        else {
+            return;
+        };
+
+        let hex = led_hex(led, self.channel_order);
+        ctx.output_mut(|output| output.copied_text = hex.clone());
+        self.show_toast(format!("Copied {hex}"));
+    }
+
+    /// Copy the color of the LED at a position within the given rectangle to the clipboard, from the frame shown for
+    /// the plugin at the given index
+    fn copy_plugin_led_color(&mut self, ctx: &Context, index: usize, rect: Rect, pos: Pos2) {
+        let frame = (self.frame_transform)(&self.plugins[index].displayed_frame());
+        self.copy_led_color(ctx, rect, &frame, pos);
+    }
+
+    /// Draw a frame that has already been transformed for display, centered within the given rectangle
+    ///
+    /// The ID names the matrix being drawn, so that its texture can be reused the next time it is drawn.
+    fn draw_frame(&self, painter: &Painter, rect: Rect, id: Id, frame: &[Vec<[u8; 4]>]) {
+        // Lay out the LEDs within the rectangle
+        let displayed_dimensions = self
+            .display_transform
+            .displayed_dimensions(self.matrix_config.dimensions());
+        let (grid, led_size) = self.led_grid(rect);
+
+        // Round the corners of the LEDs, but never so much that they become circles
+        let led_rounding = Rounding::same(self.led_rounding.min(led_size.min_elem() / 2.0));
+        let to_screen = RectTransform::from_to(Rect::from_min_size(Pos2::ZERO, grid.size()), grid);
+
+        // Build the gamma correction table, if enabled, and remap each channel with the lookup tables before correcting it
+        let gamma_table = self.gamma_enabled.then(|| gamma_table(self.gamma));
+        let lut = self.lut.filter(|_| self.lut_enabled);
+        let correct = |channel: usize, value: u8| {
+            let value = lut.map_or(value, |lut| lut[channel][value as usize]);
+            match &gamma_table {
+                Some(table) => table[value as usize],
+                None => value,
+            }
+        };
+
+        // Convert an LED into the color to draw it with, blended over the background if previewing alpha
+        let [red, green, blue, alpha] = self.channel_order;
+        let background = self.blend_background.then_some(self.background);
+        let led_color = |led: &[u8; 4]| {
+            let (r, g, b) = (
+                correct(0, led[red]),
+                correct(1, led[green]),
+                correct(2, led[blue]),
+            );
+            let color = match self.alpha_mode {
+                AlphaMode::Premultiplied => Color32::from_rgba_premultiplied(r, g, b, led[alpha]),
+                AlphaMode::Straight => Color32::from_rgba_unmultiplied(r, g, b, led[alpha]),
+                AlphaMode::Ignore => Color32::from_rgba_premultiplied(r, g, b, 255),
+            };
+            match background {
+                Some(background) => {
+                    let [r, g, b] = blend_over(color.to_array(), background);
+                    Color32::from_rgb(r, g, b)
+                }
+                None => color,
+            }
+        };
+        let cell = |x: usize, y: usize| {
+            Rect::from_min_size(
+                to_screen.transform_pos(Pos2::new(x as f32 * led_size.x, y as f32 * led_size.y)),
+                led_size,
+            )
+        };
+
+        // Fill the matrix with the background, so that it also shows around and between the LEDs
+        if let Some([r, g, b]) = background {
+            painter.rect_filled(grid, Rounding::ZERO, Color32::from_rgb(r, g, b));
+        }
+
+        // Draw plain LEDs from a texture, if tracking which LEDs changed
+        if self.dirty_tracking && !self.bloom_enabled && self.led_rounding == 0.0 {
+            let (width, height) = displayed_dimensions;
+            let colors = (0..height)
+                .flat_map(|y| {
+                    (0..width).map(move |x| {
+                        frame
+                            .get(y)
+                            .and_then(|row| row.get(x))
+                            .map_or(Color32::TRANSPARENT, led_color)
+                    })
+                })
+                .collect();
+            self.draw_texture(painter, id, grid, displayed_dimensions, colors);
+            return;
+        }
 
-    /// Network hosts that the plugin may communicate with
-    allowed_hosts: Vec<String>,
+        // Build a single mesh of every square, drawing it in one go rather than shape by shape
+        let mut mesh = Mesh::default();
+        let mut rounded_leds = vec![];
 
-    /// Map a location on the host filesystem to the plugin filesystem
-    path_maps: Vec<(PathBuf, PathBuf)>,
+        // Add the glow underneath the LEDs, shrinking the LEDs so that the glow shows between them
+        let led_scale = if self.bloom_enabled {
+            for (y, row) in bloom_frame(frame)
+                .iter()
+                .enumerate()
+                .take(displayed_dimensions.1)
+            {
+                for (x, glow) in row.iter().enumerate().take(displayed_dimensions.0) {
+                    mesh.add_colored_rect(cell(x, y), led_color(glow));
+                }
+            }
+            BLOOM_LED_SCALE
+        } else {
+            1.0
+        };
 
-    /// The last frame retrieved from the plugin
-    frame: Arc<Mutex<Vec<Vec<[u8; 4]>>>>,
+        for (y, row) in frame.iter().enumerate().take(displayed_dimensions.1) {
+            for (x, led) in row.iter().enumerate().take(displayed_dimensions.0) {
+                // Add the LED, with the color from the last update. Rounded LEDs can't join the mesh.
+                let led_rect = cell(x, y);
+                let led_rect =
+                    Rect::from_center_size(led_rect.center(), led_rect.size() * led_scale);
+                if self.led_rounding == 0.0 {
+                    mesh.add_colored_rect(led_rect, led_color(led));
+                } else {
+                    rounded_leds.push(Shape::rect_filled(led_rect, led_rounding, led_color(led)));
+                }
+            }
+        }
 
-    /// The dimensions of the matrix (width in number of LEDs, height in number of LEDs)
-    matrix_dimensions: (usize, usize),
+        painter.add(mesh);
+        painter.extend(rounded_leds);
+    }
 
-    /// Frames per second
-    fps: f32,
+    fn matrix_settings(&mut self, ui: &mut Ui) {
+        // Add matrix dimension controls
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.pending_dimensions.0)
+                    .clamp_range(1..=MAX_MATRIX_SIDELENGTH)
+                    .prefix("Width: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.pending_dimensions.1)
+                    .clamp_range(1..=MAX_MATRIX_SIDELENGTH)
+                    .prefix("Height: "),
+            );
+        });
+        if ui
+            .add_enabled(
+                self.pending_dimensions != self.matrix_config.dimensions(),
+                egui::Button::new("Resize and restart"),
+            )
+            .clicked()
+        {
+            self.resize_matrix(self.pending_dimensions.0, self.pending_dimensions.1);
+            ui.close_menu();
+        }
 
-    /// If true, a new plugin thread should be created
-    create_plugin_thread: bool,
+        // Add frame rate control, which takes effect without a restart
+        self.fps_control(ui);
+    }
 
-    /// If true, the plugin thread should generate a new frame
-    generate_frame: Arc<Mutex<bool>>,
+    /// Add a control for the frame rate, which takes effect without restarting the plugins
+    fn fps_control(&mut self, ui: &mut Ui) {
+        let mut fps = self.fps();
+        if ui
+            .add(
+                egui::DragValue::new(&mut fps)
+                    .clamp_range(1.0..=MAX_FPS)
+                    .prefix("FPS: "),
+            )
+            .on_hover_text("Change how often frames are generated during autoplay ('[' and ']'). Plugins are only told the new FPS when restarted.")
+            .changed()
+        {
+            self.set_fps(fps);
+        }
+    }
 
-    /// If true, the plugin thread should automatically generate new frames, no matter what `generate_frame` is
-    autoplay: Arc<Mutex<bool>>,
+    fn plugin_settings(&mut self, ui: &mut Ui) {
+        // List the hosts that the plugins may communicate with, each with a button to remove it
+        ui.label("Allowed hosts").on_hover_text(
+            "Hosts that plugins may make HTTP requests to. Wildcards are allowed, i.e. *.example.com.",
+        );
+        if self.allowed_hosts.is_empty() {
+            ui.weak("None");
+        }
+        let mut removed = None;
+        for (index, host) in self.allowed_hosts.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Remove host").clicked() {
+                    removed = Some(index);
+                }
+                ui.label(host);
+            });
+        }
+        if let Some(index) = removed {
+            let host = self.allowed_hosts.remove(index);
+            log::info!("Removed {host} from the allowed hosts.");
+        }
 
-    /// If true, do not allow the user to continue to interact with the UI
-    freeze: Arc<Mutex<bool>>,
+        // Add the typed host, when Enter or the button is pressed
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.new_allowed_host)
+                    .hint_text("example.com")
+                    .desired_width(160.0),
+            );
+            let entered = response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter));
+            if ui.button("Add").clicked() || entered {
+                let host = self.new_allowed_host.trim().to_string();
+                if !host.is_empty() && !self.allowed_hosts.contains(&host) {
+                    log::info!("Added {host} to the allowed hosts.");
+                    self.allowed_hosts.push(host);
+                }
+                self.new_allowed_host.clear();
+            }
+        });
 
-    /// If true, tell the current plugin thread to quit
-    stop_plugin_thread: Arc<Mutex<bool>>,
-}
+        // Point out when the running plugin was given different hosts, until it is restarted
+        if self.controlled_plugins()[0].allowed_hosts != self.allowed_hosts {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Restart the plugins to apply the changed hosts",
+            );
+        }
 
-/// Utility functions
-impl Simulator {
-    /// Create a new simulator for a plugin
-    ///
-    /// # Arguments
-    ///
-    /// * `path` - Path to the plugin to simulate
-    /// * `matrix_dimensions` - The dimensions of the matrix. Width, then height.
-    /// * `fps` - Frames per second
-    /// * `allowed_hosts` - Hosts to allow the plugin to communicate with
-    /// * `path_maps` - Local paths to map to the plugin filesystem, as two paths separated by a '>'.
-    pub(crate) fn new(
-        path: PathBuf,
-        matrix_dimensions: (usize, usize),
-        fps: f32,
-        allowed_hosts: Vec<String>,
-        path_maps: Vec<(PathBuf, PathBuf)>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            path,
-            allowed_hosts,
-            path_maps,
-            frame: Arc::new(Mutex::new(vec![
-                vec![[0; 4]; matrix_dimensions.0];
-                matrix_dimensions.1
-            ])),
-            matrix_dimensions,
-            fps,
-            create_plugin_thread: true,
-            generate_frame: Arc::new(Mutex::new(false)),
-            autoplay: Arc::new(Mutex::new(false)),
-            freeze: Arc::new(Mutex::new(false)),
-            stop_plugin_thread: Arc::new(Mutex::new(false)),
-        })
+        // List the local paths that the plugins can see, and where they see them
+        ui.separator();
+        ui.label("Mapped paths");
+        if self.path_maps.is_empty() {
+            ui.weak("None");
+        }
+        for map in &self.path_maps {
+            let access = if map.read_only {
+                " (read-only copy)"
+            } else {
+                ""
+            };
+            ui.label(format!(
+                "{} → {}{access}",
+                map.local.display(),
+                map.plugin.display()
+            ));
+        }
     }
 
-    fn spawn_thread(&mut self) -> Result<(), Box<dyn Error>> {
-        log::info!("Spawning a new plugin thread.");
+    fn display_settings(&mut self, ui: &mut Ui) {
+        // Add gamma correction controls
+        ui.checkbox(&mut self.gamma_enabled, "Gamma correction")
+            .on_hover_text(
+                "Correct colors to look more like a real LED panel. Only affects the display.",
+            );
+        ui.add_enabled(
+            self.gamma_enabled,
+            egui::Slider::new(&mut self.gamma, 0.1..=4.0).text("Gamma"),
+        );
 
-        // Reset relevant plugin flags
-        self.create_plugin_thread = false;
-        {
-            *self.stop_plugin_thread.lock().unwrap() = false;
+        // Add test pattern controls. Turning the pattern off is only possible if it was shown in place of a plugin.
+        ui.horizontal(|ui| {
+            let current_pattern = self.test_pattern();
+            let can_turn_off =
+                current_pattern.is_none() || self.controlled_plugins()[0].replaced_source.is_some();
+            let mut test_pattern = current_pattern;
+            egui::ComboBox::from_label("Test pattern")
+                .selected_text(test_pattern.map_or("Off", TestPattern::name))
+                .show_ui(ui, |ui| {
+                    if can_turn_off {
+                        ui.selectable_value(&mut test_pattern, None, "Off");
+                    }
+                    for &pattern in TestPattern::value_variants() {
+                        ui.selectable_value(&mut test_pattern, Some(pattern), pattern.name());
+                    }
+                });
+            if test_pattern != current_pattern {
+                self.show_test_pattern(test_pattern);
+            }
+            if ui
+                .color_edit_button_srgba_unmultiplied(&mut self.test_pattern_color)
+                .changed()
+            {
+                self.restart_test_patterns();
+            }
+        })
+        .response
+        .on_hover_text("Play a pattern in place of the plugin, to check the display against real hardware. Turn the pattern off to go back to the plugin.");
+
+        // Add color lookup table toggle, if one was loaded
+        ui.add_enabled(
+            self.lut.is_some(),
+            egui::Checkbox::new(&mut self.lut_enabled, "Color lookup table"),
+        )
+        .on_hover_text("Remap colors with the lookup tables given by --lut, to simulate the color cast of a panel. Only affects the display.")
+        .on_disabled_hover_text("Pass --lut to load lookup tables");
+
+        // Add LED corner rounding control
+        ui.add(
+            egui::Slider::new(&mut self.led_rounding, 0.0..=MAX_LED_ROUNDING).text("LED rounding"),
+        )
+        .on_hover_text("Round the corners of each LED, up to half of its size");
+
+        // Add alpha mode controls
+        ui.horizontal(|ui| {
+            ui.label("Alpha:");
+            for alpha_mode in [AlphaMode::Premultiplied, AlphaMode::Straight, AlphaMode::Ignore] {
+                ui.radio_value(&mut self.alpha_mode, alpha_mode, alpha_mode.name());
+            }
+        })
+        .response
+        .on_hover_text("How to display the alpha channel of each LED (A). Choose ignore if LEDs with an unset alpha look wrong.");
+
+        // Add background controls, for previewing how transparent LEDs composite
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.blend_background, "Blend alpha over background");
+            ui.color_edit_button_srgb(&mut self.background);
+        })
+        .response
+        .on_hover_text("Composite each LED over the background color, to preview transparency. Choose the ignore alpha mode (A) to force every LED opaque instead.");
+
+        // Add bloom toggle
+        ui.checkbox(&mut self.bloom_enabled, "Bloom")
+            .on_hover_text("Draw a soft glow around each LED, like the light bleed of a real LED panel. Costs extra CPU.");
+
+        // Add diff threshold control, when comparing two plugins
+        if self.view == PluginView::Diff {
+            ui.add(egui::Slider::new(&mut self.diff_threshold, 0..=255).text("Diff threshold"))
+                .on_hover_text(
+                    "LEDs whose channels differ by no more than this are considered matching",
+                );
+            ui.checkbox(&mut self.diff_overlay, "Overlay difference")
+                .on_hover_text(format!(
+                    "Show only the difference, with each channel scaled ×{DIFF_OVERLAY_SCALE} so small differences stand out"
+                ));
         }
+
+        // Add restart behavior control
+        ui.checkbox(&mut self.clear_on_restart, "Clear matrix on restart")
+            .on_hover_text("If unchecked, the last frame stays visible until the restarted plugin produces a frame");
+
+        // Add 1:1 snap controls
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.snap_led_pixels)
+                    .clamp_range(1..=MAX_SNAP_LED_PIXELS)
+                    .suffix(" px per LED"),
+            );
+            if ui
+                .button("1:1 snap")
+                .on_hover_text(
+                    "Resize the window so that each LED is a whole number of pixels across",
+                )
+                .clicked()
+            {
+                self.snap_requested = true;
+                ui.close_menu();
+            }
+        });
+
+        // Add reset button, which leaves the plugins running
+        ui.separator();
+        if ui
+            .button("Reset view & display settings")
+            .on_hover_text("Put the FPS, gamma, color lookup table, LED rounding, alpha mode, background, bloom, and diff settings back to how they were when Simtricks started, without restarting the plugins")
+            .clicked()
         {
-            *self.generate_frame.lock().unwrap() = true;
+            self.reset_display_settings();
+            ui.close_menu();
         }
+    }
 
-        // Pull WASM data from the given file
-        let wasm_data = read(self.path.clone())?;
-        let wasm = Wasm::from(wasm_data);
+    /// Get the current display settings
+    fn display_settings_now(&self) -> DisplaySettings {
+        DisplaySettings {
+            fps: self.fps,
+            gamma_enabled: self.gamma_enabled,
+            gamma: self.gamma,
+            lut_enabled: self.lut_enabled,
+            led_rounding: self.led_rounding,
+            alpha_mode: self.alpha_mode,
+            blend_background: self.blend_background,
+            background: self.background,
+            bloom_enabled: self.bloom_enabled,
+            diff_threshold: self.diff_threshold,
+            diff_overlay: self.diff_overlay,
+        }
+    }
 
-        // Create a new manifest for the plugin
-        let manifest = Manifest::new([wasm])
-            .with_allowed_hosts(self.allowed_hosts.clone().into_iter())
-            .with_allowed_paths(self.path_maps.clone().into_iter());
+    /// Put the display settings back to how they were when the simulator started, leaving the plugins running
+    fn reset_display_settings(&mut self) {
+        let settings = self.launch_display_settings.clone();
 
-        // Create the config
-        let mut matricks_config: BTreeMap<String, Option<String>> = BTreeMap::new();
-        matricks_config.insert(
-            String::from("width"),
-            Some(format!("{}", self.matrix_dimensions.0)),
-        );
-        matricks_config.insert(
-            String::from("height"),
-            Some(format!("{}", self.matrix_dimensions.1)),
-        );
-        matricks_config.insert(String::from("target_fps"), Some(format!("{}", self.fps)));
-        matricks_config.insert(String::from("serpentine"), Some(format!("{}", true)));
-        matricks_config.insert(String::from("brightness"), Some(format!("{}", 255u8)));
-
-        // Setup the host functions
-        let plugin_debug_log_function = Function::new(
-            "matricks_debug",
-            [ValType::I64],
-            [],
-            None,
-            plugin_logs::plugin_debug_log,
-        );
-        let plugin_info_log_function = Function::new(
-            "matricks_info",
-            [ValType::I64],
-            [],
-            None,
-            plugin_logs::plugin_info_log,
-        );
-        let plugin_warn_log_function = Function::new(
-            "matricks_warn",
-            [ValType::I64],
-            [],
-            None,
-            plugin_logs::plugin_warn_log,
-        );
-        let plugin_error_log_function = Function::new(
-            "matricks_error",
-            [ValType::I64],
-            [],
-            None,
-            plugin_logs::plugin_error_log,
-        );
-        let plugin_functions = [
-            plugin_debug_log_function,
-            plugin_info_log_function,
-            plugin_warn_log_function,
-            plugin_error_log_function,
-        ];
+        self.set_fps(settings.fps);
+        self.gamma_enabled = settings.gamma_enabled;
+        self.gamma = settings.gamma;
+        self.lut_enabled = settings.lut_enabled;
+        self.led_rounding = settings.led_rounding;
+        self.alpha_mode = settings.alpha_mode;
+        self.blend_background = settings.blend_background;
+        self.background = settings.background;
+        self.bloom_enabled = settings.bloom_enabled;
+        self.diff_threshold = settings.diff_threshold;
+        self.diff_overlay = settings.diff_overlay;
+        self.show_toast("Reset display settings");
+    }
+
+    fn toast(&mut self, ctx: &Context) {
+        // Forget the toast once it has been shown for long enough
+        if let Some((_, shown_at)) = &self.toast {
+            if shown_at.elapsed() > TOAST_DURATION {
+                self.toast = None;
+            }
+        }
 
-        // Create the plugin
-        let plugin = Plugin::create_with_manifest(&manifest, plugin_functions.clone(), true)?
-            .with_config(&matricks_config)?;
+        if let Some((message, shown_at)) = &self.toast {
+            // Redraw once the toast has expired, in case nothing else does
+            ctx.request_repaint_after(TOAST_DURATION.saturating_sub(shown_at.elapsed()));
 
-        // Setup and spawn the plugin thread
-        {
-            let frame = Arc::clone(&self.frame);
-            let generate_frame = Arc::clone(&self.generate_frame);
-            let autoplay = Arc::clone(&self.autoplay);
-            let freeze = Arc::clone(&self.freeze);
-            let stop_plugin_thread = Arc::clone(&self.stop_plugin_thread);
-            let fps = self.fps.clone();
-            thread::spawn(move || {
-                plugin_thread(
-                    plugin,
-                    fps,
+            egui::Area::new("toast")
+                .anchor(Align2::CENTER_BOTTOM, Vec2::new(0.0, -40.0))
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(message.as_str());
+                    });
+                });
+        }
+    }
+
+    fn latency_panel(&mut self, ctx: &Context) {
+        let samples = self.controlled_plugins()[0].latency.lock().unwrap().clone();
+
+        egui::Window::new("Update latency")
+            .open(&mut self.latency_panel_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                // Show the percentiles, in milliseconds
+                let to_ms = |micros: u128| micros as f64 / 1000.0;
+                ui.horizontal(|ui| {
+                    for (label, percent) in [("p50", 50.0), ("p95", 95.0), ("p99", 99.0)] {
+                        let value = percentile(&samples, percent)
+                            .map_or(String::from("-"), |micros| {
+                                format!("{:.2} ms", to_ms(micros))
+                            });
+                        ui.label(format!("{label}: {value}"));
+                    }
+                });
+
+                // Draw a bar for each bin, scaled to the fullest bin
+                let (response, painter) = ui.allocate_painter(LATENCY_PLOT_SIZE, Sense::hover());
+                let rect = response.rect;
+                painter.rect_filled(rect, Rounding::ZERO, ui.visuals().extreme_bg_color);
+                let counts = histogram(&samples, LATENCY_BINS);
+                let fullest = counts.iter().copied().max().unwrap_or(0).max(1);
+                let bar_width = rect.width() / LATENCY_BINS as f32;
+                for (bin, &count) in counts.iter().enumerate() {
+                    let height = rect.height() * count as f32 / fullest as f32;
+                    let left = rect.left() + bin as f32 * bar_width;
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            Pos2::new(left, rect.bottom() - height),
+                            Pos2::new(left + bar_width - 1.0, rect.bottom()),
+                        ),
+                        Rounding::ZERO,
+                        ui.visuals().selection.bg_fill,
+                    );
+                }
+
+                // Label the range of the plot
+                let slowest = samples.iter().copied().max().unwrap_or(0);
+                ui.horizontal(|ui| {
+                    ui.label("0 ms");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(format!("{:.2} ms", to_ms(slowest)));
+                    });
+                });
+                ui.label(format!("{} most recent updates", samples.len()));
+            });
+    }
+
+    fn color_panel(&mut self, ctx: &Context) {
+        // Count the colors again when the frame changes, but no more often than the interval allows
+        let frame = self.controlled_plugins()[0].displayed_frame();
+        let wait = match &self.color_stats {
+            Some(stats) if Arc::ptr_eq(&stats.frame, &frame) => None,
+            Some(stats) => Some(COLOR_STATS_INTERVAL.saturating_sub(stats.counted_at.elapsed())),
+            None => Some(Duration::ZERO),
+        };
+        match wait {
+            Some(wait) if wait.is_zero() => {
+                self.color_stats = Some(ColorStats {
+                    histogram: color_histogram(&frame, self.channel_order, COLOR_HISTOGRAM_LEVELS),
+                    top_colors: most_common_colors(&frame, self.channel_order, COLOR_SWATCHES),
                     frame,
-                    generate_frame,
-                    autoplay,
-                    freeze,
-                    stop_plugin_thread,
-                )
+                    counted_at: Instant::now(),
+                });
+            }
+            // Come back once the interval is up, so the panel catches up with the latest frame
+            Some(wait) => ctx.request_repaint_after(wait),
+            None => {}
+        }
+        let Some(stats) = &self.color_stats else {
+            return;
+        };
+
+        egui::Window::new("Colors")
+            .open(&mut self.color_panel_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                // Draw a bar for each bucket of colors, in the color in the middle of the bucket, scaled to the fullest
+                // bucket
+                let (response, painter) = ui.allocate_painter(COLOR_PLOT_SIZE, Sense::hover());
+                let rect = response.rect;
+                painter.rect_filled(rect, Rounding::ZERO, ui.visuals().extreme_bg_color);
+                let fullest = stats.histogram.iter().copied().max().unwrap_or(0).max(1);
+                let bar_width = rect.width() / stats.histogram.len() as f32;
+                for (bucket, &count) in stats.histogram.iter().enumerate() {
+                    let [red, green, blue] = histogram_bucket_color(bucket, COLOR_HISTOGRAM_LEVELS);
+                    let height = rect.height() * count as f32 / fullest as f32;
+                    let left = rect.left() + bucket as f32 * bar_width;
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            Pos2::new(left, rect.bottom() - height),
+                            Pos2::new(left + bar_width - 1.0, rect.bottom()),
+                        ),
+                        Rounding::ZERO,
+                        Color32::from_rgb(red, green, blue),
+                    );
+                }
+
+                // Show the most common colors as swatches, ignoring alpha
+                ui.label("Most common colors");
+                ui.horizontal_wrapped(|ui| {
+                    for ([red, green, blue, alpha], count) in &stats.top_colors {
+                        let (rect, response) =
+                            ui.allocate_exact_size(COLOR_SWATCH_SIZE, Sense::hover());
+                        ui.painter().rect_filled(
+                            rect,
+                            Rounding::ZERO,
+                            Color32::from_rgb(*red, *green, *blue),
+                        );
+                        response.on_hover_text(format!(
+                            "#{red:02X}{green:02X}{blue:02X}{alpha:02X}: {count} LEDs"
+                        ));
+                    }
+                });
             });
+    }
+
+    fn channel_panel(&mut self, ctx: &Context) {
+        // Work out the channel values again whenever the frame changes
+        let frame = self.controlled_plugins()[0].displayed_frame();
+        if !self
+            .channel_stats
+            .as_ref()
+            .is_some_and(|(counted, _)| Arc::ptr_eq(counted, &frame))
+        {
+            self.channel_stats = Some((
+                Arc::clone(&frame),
+                channel_stats(&frame, self.channel_order),
+            ));
         }
+        let Some((_, stats)) = &self.channel_stats else {
+            return;
+        };
 
-        Ok(())
+        egui::SidePanel::right("channels")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Channels");
+                egui::Grid::new("channel_stats")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        ui.label("Min");
+                        ui.label("Max");
+                        ui.label("Mean");
+                        ui.end_row();
+
+                        for (name, color, channel) in [
+                            ("Red", Color32::RED, stats[0]),
+                            ("Green", Color32::GREEN, stats[1]),
+                            ("Blue", Color32::LIGHT_BLUE, stats[2]),
+                        ] {
+                            ui.colored_label(color, name);
+                            ui.label(channel.min.to_string());
+                            ui.label(channel.max.to_string());
+                            ui.label(format!("{:.1}", channel.mean));
+                            ui.end_row();
+                        }
+                    });
+
+                // Point out channels that are never lit, which are easy to miss by eye
+                for (name, channel) in ["Red", "Green", "Blue"].iter().zip(stats) {
+                    if channel.max == 0 {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("{name} is never lit"),
+                        );
+                    }
+                }
+            });
     }
-}
 
-/// Control functions
-impl Simulator {
-    /// Play/pause the plugin
-    fn toggle_autoplay(&mut self) {
-        let mut autoplay = self.autoplay.lock().unwrap();
-        *autoplay = !*autoplay;
+    fn log_panel(&mut self, ctx: &Context) {
+        let logs = Arc::clone(&self.controlled_plugins()[0].logs);
+
+        egui::Window::new("Plugin logs")
+            .open(&mut self.log_panel_open)
+            .default_size(LOG_PANEL_SIZE)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} most recent logs", logs.lock().unwrap().len()));
+                    if ui.button("Clear").clicked() {
+                        logs.lock().unwrap().clear();
+                    }
+                });
+                ui.separator();
+
+                // Show each log colored by its level, keeping the newest log in view as logs arrive
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for (level, message) in logs.lock().unwrap().iter() {
+                            let color = match level {
+                                Level::Error => ui.visuals().error_fg_color,
+                                Level::Warn => ui.visuals().warn_fg_color,
+                                Level::Info => ui.visuals().text_color(),
+                                Level::Debug | Level::Trace => ui.visuals().weak_text_color(),
+                            };
+                            ui.colored_label(color, format!("{level:<5} {message}"));
+                        }
+                    });
+            });
     }
 
-    /// Go to the next frame
-    fn step(&mut self) {
-        // Tell the plugin update thread to generate a new frame
-        let mut generate_frame_flag = self.generate_frame.lock().unwrap();
-        *generate_frame_flag = true;
+    fn menu_bar(&mut self, ctx: &Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open...").clicked() {
+                        ui.close_menu();
+                        self.pick_plugin();
+                    }
+                    if ui.button("Open URL...").clicked() {
+                        self.open_dialog = Some(String::new());
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Add Plugin...")
+                        .on_hover_text(
+                            "Open another plugin in a new tab, alongside the current plugins",
+                        )
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        self.pick_added_plugin();
+                    }
+                    if ui
+                        .add(egui::Button::new("Quit").shortcut_text("Ctrl+Q"))
+                        .clicked()
+                    {
+                        self.quit_requested = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
     }
 
-    /// Kill the current plugin thread and create a new one
-    fn restart(&mut self) {
-        // Clear the current frame
-        {
-            *self.frame.lock().unwrap() =
-                vec![vec![[0; 4]; self.matrix_dimensions.0]; self.matrix_dimensions.1];
+    fn open_dialog(&mut self, ctx: &Context) {
+        let Some(source) = &mut self.open_dialog else {
+            return;
+        };
+
+        let mut open = false;
+        let mut cancel = false;
+        egui::Window::new("Open plugin")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Path to plugin, or an HTTP(S) URL to download it from:");
+                let response = ui.text_edit_singleline(source);
+                response.request_focus();
+                if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+                    open = true;
+                }
+                ui.horizontal(|ui| {
+                    open |= ui.button("Open (Enter)").clicked();
+                    cancel = ui.button("Cancel (Esc)").clicked();
+                });
+            });
+
+        if open && !source.trim().is_empty() {
+            let source = PluginSource::parse(source.trim());
+            self.open_dialog = None;
+            self.open_plugin(source);
+        } else if cancel {
+            self.open_dialog = None;
         }
+    }
 
-        // Signal that the existing plugin thread should be stopped
-        {
-            *self.stop_plugin_thread.lock().unwrap() = true;
+    fn restart_dialog(&mut self, ctx: &Context) {
+        if !self.restart_dialog_open {
+            return;
         }
 
-        // Signal that a new plugin thread should be created
-        self.create_plugin_thread = true;
+        egui::Window::new("Restart plugin?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("The current state of the plugin will be lost.");
+                ui.checkbox(&mut self.confirm_restart, "Ask before restarting");
+                ui.horizontal(|ui| {
+                    if ui.button("Restart (Enter)").clicked() {
+                        self.restart_dialog_open = false;
+                        self.restart();
+                    }
+                    if ui.button("Cancel (Esc)").clicked() {
+                        self.restart_dialog_open = false;
+                    }
+                });
+            });
     }
 
-    /// Handle any keyboard shortcuts
-    fn consume_shortcuts(&mut self, ctx: &Context) {
-        ctx.input_mut(|input_state| {
-            // If space is pressed, toggle autoplay
-            if input_state.consume_key(Modifiers::NONE, Key::Space) {
-                self.toggle_autoplay();
-            }
+    /// Draw the status bar
+    ///
+    /// # Arguments
+    ///
+    /// * `difference` - Where the two plugins differ, in the diff view
+    fn status_bar(&mut self, ctx: &Context, difference: Option<&Difference>) {
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Show the file and state of each plugin, with the full path or URL on hover
+                for plugin in self.controlled_plugins() {
+                    ui.label(format!(
+                        "{} ({})",
+                        plugin.source.file_name(),
+                        plugin.status()
+                    ))
+                    .on_hover_text(plugin.source.to_string());
+                }
 
-            // If 'N' or right arrow is pressed and autoplay is off, step to the next frame
-            if (input_state.consume_key(Modifiers::NONE, Key::N)
-                || input_state.consume_key(Modifiers::NONE, Key::ArrowRight))
-                && !*self.autoplay.lock().unwrap()
-            {
-                self.step();
-            }
+                // Show the dimensions of the matrix
+                ui.separator();
+                let (width, height) = self.matrix_config.dimensions();
+                ui.label(format!("{width}x{height}"));
 
-            // If 'R' is pressed, restart the plugin
-            if input_state.consume_key(Modifiers::NONE, Key::R) {
-                self.restart()
-            }
+                // Show how full the frame history is
+                ui.separator();
+                let history_length = self.history_length();
+                ui.label(format!(
+                    "History: {history_length}/{} frames",
+                    self.history_size
+                ));
+                let history_offset = self.history_offset();
+                if history_offset > 0 {
+                    ui.label(format!("(viewing {history_offset} back)"));
+                }
+
+                // Show how many LEDs differ between the two plugins in the diff view, and by how much
+                if let Some(difference) = difference {
+                    ui.separator();
+                    ui.label(format!("Differing LEDs: {}", difference.differing_leds));
+                    ui.label(format!("Total diff: {}", difference.total))
+                        .on_hover_text(
+                            "Sum of the absolute differences between every channel of every LED",
+                        );
+                }
+
+                // Show the target FPS, along with any frame rate requested by the plugins
+                ui.separator();
+                ui.label(format!("FPS: {}", self.fps()))
+                    .on_hover_text(format!(
+                        "Plugins were told to target {} FPS when they were last restarted",
+                        self.matrix_config.target_fps
+                    ));
+                for plugin in self.controlled_plugins() {
+                    if let Some(requested_fps) = *plugin.requested_fps.lock().unwrap() {
+                        let used = if self.respect_plugin_fps {
+                            "in use"
+                        } else {
+                            "ignored"
+                        };
+                        ui.label(format!("(plugin requested {requested_fps}, {used})"))
+                            .on_hover_text(
+                                "Pass --respect-plugin-fps to run at the requested frame rate",
+                            );
+                    }
+                }
+
+                // Show how many frames have been dropped because the plugins were too slow
+                let dropped_frames: u64 = self
+                    .controlled_plugins()
+                    .iter()
+                    .map(|plugin| plugin.dropped_frames)
+                    .sum();
+                if dropped_frames > 0 {
+                    ui.separator();
+                    ui.label(format!("Dropped frames: {dropped_frames}"))
+                        .on_hover_text("Number of frame deadlines missed during autoplay");
+                }
+
+                // Warn if any of the plugins are consistently falling behind
+                if let Some(fps) = self
+                    .controlled_plugins()
+                    .iter()
+                    .find_map(|plugin| plugin.too_slow_for)
+                {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("Dropping frames - plugin too slow for {fps} FPS"),
+                    )
+                    .on_hover_text("Lower the FPS to a rate the plugin can keep up with");
+                }
+            });
         });
     }
-}
 
-/// GUI functions
-impl Simulator {
-    fn matrix(&mut self, ctx: &Context) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Allocate our painter
-            let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click());
+    fn plugin_tabs(&mut self, ctx: &Context) {
+        // Tabs are only useful when there is more than one plugin
+        if self.plugins.len() < 2 {
+            return;
+        }
 
-            // Get the relative position of the painter
-            let to_screen = RectTransform::from_to(
-                Rect::from_min_size(Pos2::ZERO, response.rect.size()),
-                response.rect,
-            );
+        egui::TopBottomPanel::top("plugin_tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Add side by side and diff views, which return every plugin to its latest frame so they are back in step
+                let mut lockstep_views = vec![(
+                    PluginView::SideBySide,
+                    "Side by side",
+                    "Show every plugin, controlled together",
+                )];
+                if self.plugins.len() == 2 {
+                    lockstep_views.push((
+                        PluginView::Diff,
+                        "Diff",
+                        "Show both plugins, controlled together, and the LEDs where they differ",
+                    ));
+                }
+                for (view, label, hover_text) in lockstep_views {
+                    if ui
+                        .selectable_label(self.view == view, label)
+                        .on_hover_text(hover_text)
+                        .clicked()
+                    {
+                        self.view = view;
+                        for plugin in &mut self.plugins {
+                            plugin.history_offset = 0;
+                        }
+                    }
+                }
 
-            // Calculate the LED sidelength for x and y based on the window size and number of pixels, and choose smallest value for LED sidelength
-            let sidelength = [
-                response.rect.width() / self.matrix_dimensions.0 as f32, // Sidelength from width
-                response.rect.height() / self.matrix_dimensions.1 as f32, // Sidelength from height
-            ]
-            .iter()
-            .min_by(|a, b| a.partial_cmp(b).unwrap()) // Pick smaller of the two
-            .unwrap()
-            .clone(); // It's still a &f32, so clone it
-
-            // Grab the frame
-            let mut frame = self.frame.lock().unwrap();
-            let frame = frame.deref_mut();
-
-            for y in 0..self.matrix_dimensions.1 {
-                for x in 0..self.matrix_dimensions.0 {
-                    // Grab the color of this LED from the last update
-                    let led_color = egui::Color32::from_rgba_premultiplied(
-                        frame[y][x][2],
-                        frame[y][x][1],
-                        frame[y][x][0],
-                        frame[y][x][3],
-                    );
+                ui.separator();
 
-                    // Draw the LED
-                    painter.rect_filled(
-                        Rect::from_min_size(
-                            to_screen.transform_pos(Pos2::new(
-                                x as f32 * sidelength,
-                                y as f32 * sidelength,
-                            )),
-                            Vec2::new(sidelength, sidelength),
-                        ),
-                        Rounding::ZERO,
-                        led_color,
-                    );
+                // Add a tab for each plugin
+                for index in 0..self.plugins.len() {
+                    let selected = self.view == PluginView::Tabs && self.selected_plugin == index;
+                    if ui
+                        .selectable_label(selected, self.plugins[index].label())
+                        .clicked()
+                    {
+                        self.view = PluginView::Tabs;
+                        self.selected_plugin = index;
+                    }
                 }
-            }
+
+                // Add a button to add another plugin in a new tab
+                if ui
+                    .button("+")
+                    .on_hover_text("Add another plugin in a new tab")
+                    .clicked()
+                {
+                    self.pick_added_plugin();
+                }
+            });
         });
     }
 
@@ -301,8 +2780,8 @@ impl Simulator {
                 // Add autoplay toggle button
                 if ui
                     .add_enabled(
-                        !*self.freeze.lock().unwrap(),
-                        egui::ImageButton::new(if *self.autoplay.lock().unwrap() {
+                        self.is_running(),
+                        egui::ImageButton::new(if self.is_autoplaying() {
                             egui::include_image!("../assets/pause.png")
                         } else {
                             egui::include_image!("../assets/play.png")
@@ -314,28 +2793,124 @@ impl Simulator {
                     self.toggle_autoplay();
                 };
 
+                // Add step back button
+                if ui
+                    .add_enabled(
+                        !self.is_autoplaying() && self.can_step_back(),
+                        egui::ImageButton::new(egui::include_image!("../assets/step.png"))
+                            .uv(Rect::from_min_max(Pos2::new(1.0, 0.0), Pos2::new(0.0, 1.0))),
+                    )
+                    .on_hover_text("Step back to previous frame (B)")
+                    .clicked()
+                {
+                    self.step_back();
+                }
+
                 // Add step button
                 if ui
                     .add_enabled(
-                        !*self.autoplay.lock().unwrap() && !*self.freeze.lock().unwrap(),
+                        !self.is_autoplaying() && self.can_step(),
                         egui::ImageButton::new(egui::include_image!("../assets/step.png")),
                     )
-                    .on_hover_text("Step to next frame (N)")
+                    .on_hover_text("Step to next frame (N), or jump 10 frames (Shift+Right)")
                     .clicked()
                 {
-                    self.step();
+                    self.step(self.step_size);
+                }
+
+                // Add step size control
+                ui.add(
+                    egui::DragValue::new(&mut self.step_size)
+                        .clamp_range(1..=MAX_STEP_SIZE)
+                        .suffix("×"),
+                )
+                .on_hover_text("Number of frames to advance with each step");
+
+                // Add matrix dimension controls, resizing once a value has been dragged or typed in rather than on
+                // every change, so that dragging doesn't restart the plugins at each step
+                ui.separator();
+                let width = ui
+                    .add(
+                        egui::DragValue::new(&mut self.pending_dimensions.0)
+                            .clamp_range(1..=MAX_MATRIX_SIDELENGTH),
+                    )
+                    .on_hover_text("Matrix width. Changing it restarts the plugins.");
+                ui.label("×");
+                let height = ui
+                    .add(
+                        egui::DragValue::new(&mut self.pending_dimensions.1)
+                            .clamp_range(1..=MAX_MATRIX_SIDELENGTH),
+                    )
+                    .on_hover_text("Matrix height. Changing it restarts the plugins.");
+                let edited = [&width, &height]
+                    .iter()
+                    .any(|response| response.drag_released() || response.lost_focus());
+                if edited && self.pending_dimensions != self.matrix_config.dimensions() {
+                    self.resize_matrix(self.pending_dimensions.0, self.pending_dimensions.1);
                 }
 
+                // Add frame rate control
+                self.fps_control(ui);
+                ui.separator();
+
                 // Add plugin restart button
                 if ui
                     .add_enabled(
                         true,
                         egui::ImageButton::new(egui::include_image!("../assets/restart.png")),
                     )
-                    .on_hover_text("Restart plugin (R)")
+                    .on_hover_text(self.restart_hover_text())
                     .clicked()
                 {
-                    self.restart();
+                    self.request_restart();
+                }
+
+                ui.separator();
+
+                // Add matrix settings menu
+                ui.menu_button("Matrix", |ui| self.matrix_settings(ui));
+
+                // Add display settings menu
+                ui.menu_button("Display", |ui| self.display_settings(ui));
+
+                // Add plugin settings menu
+                ui.menu_button("Plugin", |ui| self.plugin_settings(ui));
+
+                // Add palette copy button
+                if ui
+                    .button("Copy Palette")
+                    .on_hover_text(
+                        "Copy the most common colors of the frame as a JSON array of hex strings",
+                    )
+                    .clicked()
+                {
+                    self.copy_palette(ctx);
+                }
+
+                // Add Rust frame copy button
+                if ui
+                    .button("Copy as Rust")
+                    .on_hover_text("Copy the frame as a Rust const array, to paste into a test as a golden value")
+                    .clicked()
+                {
+                    self.copy_frame_as_rust(ctx);
+                }
+
+                // Add break on log toggle
+                let mut break_on_log = self.break_on_log.load(Ordering::Relaxed);
+                if ui
+                    .checkbox(&mut break_on_log, "Break on error log")
+                    .on_hover_text("Pause the plugin when it makes a warn or error log")
+                    .changed()
+                {
+                    self.break_on_log.store(break_on_log, Ordering::Relaxed);
+                }
+
+                // Show that a recording is in progress
+                if self.recording.is_some() {
+                    ui.separator();
+                    ui.colored_label(Color32::RED, "● REC")
+                        .on_hover_text("Recording a GIF. Press G to stop and save it.");
                 }
             });
         });
@@ -343,32 +2918,108 @@ impl Simulator {
 }
 
 impl App for Simulator {
-    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
-        // Create a new plugin thread, if there isn't one already
-        if self.create_plugin_thread {
-            match self.spawn_thread() {
-                Ok(_) => {
-                    // Unfreeze the simulator
-                    *self.freeze.lock().unwrap() = false;
-                }
-                Err(_) => {
-                    log::error!("Failed to create a new plugin thread.");
-                    *self.freeze.lock().unwrap() = true;
-                }
-            };
+    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+        // Create new plugin threads, for any plugin waiting to restart
+        if self.plugins.iter().any(|plugin| plugin.restart_pending) {
+            self.spawn_threads(ctx);
+        }
+
+        // Catch up on everything the plugin threads have sent
+        for plugin in &mut self.plugins {
+            self.update_window_title |= plugin.receive_events();
+        }
+
+        // Pause every plugin shown side by side if one of them was paused
+        self.keep_in_lockstep();
+
+        // Update the window title, if the plugin has changed
+        if self.update_window_title {
+            frame.set_window_title(&self.window_title());
+            self.update_window_title = false;
         }
 
         // Handle keyboard shortcuts
         self.consume_shortcuts(ctx);
 
+        // Open any plugin dropped onto the window
+        self.open_dropped_file(ctx);
+
+        // Stop the plugin threads and close the window, if asked to quit
+        if self.quit_requested {
+            self.quit_requested = false;
+            log::info!("Quitting Simtricks.");
+            self.stop_plugins();
+            frame.close();
+        }
+
+        // Resize the window for a 1:1 snap, if requested
+        if self.snap_requested {
+            self.snap_requested = false;
+            self.snap_window(frame);
+        }
+
+        // Enter or leave fullscreen, if toggled
+        if self.window_fullscreen != self.fullscreen {
+            frame.set_fullscreen(self.fullscreen);
+            self.window_fullscreen = self.fullscreen;
+        }
+
+        // Record the latest frame, if recording
+        self.record_frame();
+
         // Install image loaders, if they aren't already installed
         egui_extras::install_image_loaders(ctx);
 
-        // Force a repaint
-        ctx.request_repaint();
+        // Schedule the next repaint, if asked to redraw continuously. Otherwise, plugin threads ask for a repaint on each
+        // frame. Either way, the plugin threads keep their own frame timing.
+        if self.continuous_repaint {
+            ctx.request_repaint_after(CONTINUOUS_REPAINT_INTERVAL);
+        }
+
+        // Work out where the two plugins differ once for this frame, for both the matrix and the status bar
+        let difference = (self.view == PluginView::Diff).then(|| self.difference());
+
+        // Draw the GUI, with only the matrix in fullscreen
+        if !self.fullscreen {
+            self.menu_bar(ctx);
+            self.top_panel(ctx);
+            self.plugin_tabs(ctx);
+            self.status_bar(ctx, difference.as_ref());
+            if self.channel_panel_open {
+                self.channel_panel(ctx);
+            }
+        }
+        self.matrix(ctx, difference.as_ref());
+        self.restart_dialog(ctx);
+        self.open_dialog(ctx);
+        if self.latency_panel_open {
+            self.latency_panel(ctx);
+        }
+        if self.color_panel_open {
+            self.color_panel(ctx);
+        }
+        if self.log_panel_open {
+            self.log_panel(ctx);
+        }
+        self.toast(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Let the plugin threads exit cleanly
+        self.stop_plugins();
 
-        // Draw the GUI
-        self.top_panel(ctx);
-        self.matrix(ctx);
+        // Disconnect any WebSocket clients
+        if let Some(frame_server) = &mut self.frame_server {
+            frame_server.shutdown();
+        }
     }
 }
+
+/// Returns true if the file has the extension of a plugin that can be opened, in any case
+fn is_plugin_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        PLUGIN_EXTENSIONS
+            .iter()
+            .any(|plugin_extension| extension.eq_ignore_ascii_case(plugin_extension))
+    })
+}
@@ -0,0 +1,177 @@
+use crate::color::ChannelOrder;
+use crate::plugin_thread::FrameSource;
+use crate::Frame;
+use clap::ValueEnum;
+use std::error::Error;
+use std::path::Path;
+
+/// Number of LEDs between the lines of the grid pattern
+const GRID_SPACING: usize = 4;
+
+/// A pattern to fill the matrix with in place of a plugin, for checking the display against real hardware
+///
+/// On the command line, "white" is accepted for the solid pattern, and "checker" for the checkerboard.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TestPattern {
+    /// Every LED in the chosen color
+    #[value(alias = "white")]
+    Solid,
+
+    /// Alternating LEDs in the chosen color and black
+    #[value(alias = "checker")]
+    Checkerboard,
+
+    /// The chosen color, fading in from black from left to right
+    Gradient,
+
+    /// The red channel, ramping up from 0 to 255 from left to right
+    RedRamp,
+
+    /// The green channel, ramping up from 0 to 255 from left to right
+    GreenRamp,
+
+    /// The blue channel, ramping up from 0 to 255 from left to right
+    BlueRamp,
+
+    /// Red, green, and blue bars, from left to right
+    RgbBars,
+
+    /// A column in the chosen color, moving one LED to the right with each frame
+    Sweep,
+
+    /// Dim lines every few LEDs, with the top row in red, the left column in green, and the first LED in white, to show
+    /// where the origin is and which way the axes run
+    Grid,
+}
+
+impl TestPattern {
+    /// Get the name of this pattern, as shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            TestPattern::Solid => "Solid",
+            TestPattern::Checkerboard => "Checkerboard",
+            TestPattern::Gradient => "Gradient",
+            TestPattern::RedRamp => "Red ramp",
+            TestPattern::GreenRamp => "Green ramp",
+            TestPattern::BlueRamp => "Blue ramp",
+            TestPattern::RgbBars => "RGB bars",
+            TestPattern::Sweep => "Sweep",
+            TestPattern::Grid => "Grid",
+        }
+    }
+
+    /// Build a frame of this pattern, with its LEDs in the given channel order as if a plugin had produced it
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the frame, in number of LEDs
+    /// * `height` - Height of the frame, in number of LEDs
+    /// * `color` - Color used by the solid, checkerboard, gradient, and sweep patterns, in RGBA order
+    /// * `channel_order` - Order of the color channels in each LED of the frame
+    /// * `step` - Number of frames since the pattern started, for moving patterns
+    pub fn frame(
+        self,
+        width: usize,
+        height: usize,
+        color: [u8; 4],
+        channel_order: ChannelOrder,
+        step: u64,
+    ) -> Frame {
+        // Ramp from 0 at the left edge to 255 at the right edge
+        let ramp = |x: usize| match width {
+            0 | 1 => 255,
+            _ => (x * 255 / (width - 1)) as u8,
+        };
+
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        let [red, green, blue, alpha] = color;
+                        let rgba = match self {
+                            TestPattern::Solid => color,
+                            TestPattern::Checkerboard if (x + y) % 2 == 0 => color,
+                            TestPattern::Checkerboard => [0, 0, 0, 255],
+                            TestPattern::Gradient => {
+                                let fade =
+                                    |channel: u8| (channel as usize * ramp(x) as usize / 255) as u8;
+                                [fade(red), fade(green), fade(blue), alpha]
+                            }
+                            TestPattern::RedRamp => [ramp(x), 0, 0, 255],
+                            TestPattern::GreenRamp => [0, ramp(x), 0, 255],
+                            TestPattern::BlueRamp => [0, 0, ramp(x), 255],
+                            TestPattern::RgbBars => match x * 3 / width {
+                                0 => [255, 0, 0, 255],
+                                1 => [0, 255, 0, 255],
+                                _ => [0, 0, 255, 255],
+                            },
+                            TestPattern::Sweep if step % width as u64 == x as u64 => color,
+                            TestPattern::Sweep => [0, 0, 0, 255],
+                            TestPattern::Grid => match (x, y) {
+                                (0, 0) => [255, 255, 255, 255],
+                                (_, 0) => [255, 0, 0, 255],
+                                (0, _) => [0, 255, 0, 255],
+                                _ if x % GRID_SPACING == 0 || y % GRID_SPACING == 0 => {
+                                    [64, 64, 64, 255]
+                                }
+                                _ => [0, 0, 0, 255],
+                            },
+                        };
+
+                        // Arrange the channels as a plugin would
+                        let mut led = [0; 4];
+                        for (value, index) in rgba.into_iter().zip(channel_order) {
+                            led[index] = value;
+                        }
+                        led
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Plays a test pattern in place of a plugin, from a plugin thread
+pub struct PatternPlayer {
+    /// The pattern to play
+    pub pattern: TestPattern,
+
+    /// Width and height of the frames, in number of LEDs
+    pub dimensions: (usize, usize),
+
+    /// Color used by the pattern, in RGBA order. The player is restarted to change it.
+    pub color: [u8; 4],
+
+    /// Order of the color channels in each LED of the frames
+    pub channel_order: ChannelOrder,
+
+    /// Number of frames produced so far
+    pub step: u64,
+}
+
+impl FrameSource for PatternPlayer {
+    fn setup(&mut self) -> Result<(), Box<dyn Error>> {
+        log::info!(
+            "Playing the {} test pattern.",
+            self.pattern.name().to_lowercase()
+        );
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<Option<Frame>, Box<dyn Error>> {
+        let (width, height) = self.dimensions;
+        let frame = self
+            .pattern
+            .frame(width, height, self.color, self.channel_order, self.step);
+        self.step += 1;
+        Ok(Some(frame))
+    }
+
+    fn save_state(&mut self, _path: &Path) -> Result<(), Box<dyn Error>> {
+        Err("Test patterns have no state to save.".into())
+    }
+
+    fn load_state(&mut self, _path: &Path) -> Result<(), Box<dyn Error>> {
+        Err("Test patterns have no state to restore.".into())
+    }
+}
@@ -0,0 +1,20 @@
+use simtricks::color::{blend_over, parse_hex_color};
+
+#[test]
+fn colors_blend_over_background() {
+    // Opaque colors hide the background, and clear colors show it
+    assert_eq!(blend_over([10, 20, 30, 255], [200, 200, 200]), [10, 20, 30]);
+    assert_eq!(blend_over([0, 0, 0, 0], [200, 100, 50]), [200, 100, 50]);
+
+    // Half transparent red, premultiplied, over blue
+    assert_eq!(blend_over([128, 0, 0, 128], [0, 0, 255]), [128, 0, 127]);
+}
+
+#[test]
+fn hex_colors_are_parsed() {
+    assert_eq!(parse_hex_color("#1E1E1E"), Ok([30, 30, 30]));
+    assert_eq!(parse_hex_color("ff8000"), Ok([255, 128, 0]));
+    for color in ["", "#12345", "#1234567", "#GGGGGG"] {
+        assert!(parse_hex_color(color).is_err(), "{color}");
+    }
+}
@@ -0,0 +1,17 @@
+use simtricks::color::average_frames;
+
+#[test]
+fn frames_are_averaged_per_channel() {
+    let frames = vec![
+        vec![vec![[0, 10, 255, 255]]],
+        vec![vec![[100, 20, 0, 255]]],
+        vec![vec![[200, 31, 0, 255]]],
+    ];
+    assert_eq!(average_frames(&frames), vec![vec![[100, 20, 85, 255]]]);
+}
+
+#[test]
+fn single_frame_is_unchanged() {
+    let frame = vec![vec![[1, 2, 3, 4], [5, 6, 7, 8]]];
+    assert_eq!(average_frames([&frame]), frame);
+}
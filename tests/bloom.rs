@@ -0,0 +1,17 @@
+use simtricks::color::bloom_frame;
+
+#[test]
+fn light_spreads_to_neighbors() {
+    let mut frame = vec![vec![[0; 4]; 3]; 3];
+    frame[1][1] = [200; 4];
+    let bloom = bloom_frame(&frame);
+    assert_eq!(bloom[1][1], [100; 4]);
+    assert_eq!(bloom[0][1], [30; 4]);
+    assert_eq!(bloom[0][0], [14; 4]);
+}
+
+#[test]
+fn bright_neighbors_saturate() {
+    let frame = vec![vec![[255; 4]; 3]; 3];
+    assert_eq!(bloom_frame(&frame)[1][1], [255; 4]);
+}
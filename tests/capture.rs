@@ -0,0 +1,39 @@
+use simtricks::capture::{frame_to_rust, FrameCapture, CAPTURE_LED_SIZE};
+use simtricks::color::DEFAULT_CHANNEL_ORDER;
+
+#[test]
+fn every_nth_frame_is_captured() {
+    let capture = FrameCapture {
+        every: 3,
+        dir: std::env::temp_dir().join("simtricks_capture_test"),
+        channel_order: DEFAULT_CHANNEL_ORDER,
+    };
+    let due: Vec<u64> = (1..=9).filter(|&n| capture.is_due(n)).collect();
+    assert_eq!(due, vec![3, 6, 9]);
+
+    // Capture into a directory that doesn't exist yet
+    let _ = std::fs::remove_dir_all(&capture.dir);
+    let frame = vec![vec![[255, 0, 0, 255], [0, 0, 255, 255]]];
+    let path = capture.capture(&frame, 3).unwrap();
+    assert_eq!(path, capture.dir.join("capture_000003.png"));
+
+    let image = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(
+        image.dimensions(),
+        (2 * CAPTURE_LED_SIZE as u32, CAPTURE_LED_SIZE as u32)
+    );
+    assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    assert_eq!(
+        image.get_pixel(CAPTURE_LED_SIZE as u32, 0).0,
+        [255, 0, 0, 255]
+    );
+}
+
+#[test]
+fn frame_is_formatted_as_rust() {
+    let frame = vec![vec![[1, 2, 3, 4], [5, 6, 7, 8]], vec![[0, 0, 0, 255]]];
+    assert_eq!(
+        frame_to_rust(&frame),
+        "const FRAME: &[&[[u8; 4]]] = &[\n    &[[1, 2, 3, 4], [5, 6, 7, 8]],\n    &[[0, 0, 0, 255]],\n];\n"
+    );
+}
@@ -0,0 +1,11 @@
+use simtricks::color::channel_difference;
+
+#[test]
+fn channel_difference_keeps_each_channel() {
+    let a = vec![vec![[10, 20, 30, 255]]];
+    let b = vec![vec![[15, 0, 30, 200], [1, 2, 3, 4]]];
+    assert_eq!(
+        channel_difference(&a, &b),
+        vec![vec![[5, 20, 0, 55], [1, 2, 3, 4]]]
+    );
+}
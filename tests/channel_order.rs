@@ -0,0 +1,20 @@
+use simtricks::color::{parse_channel_order, DEFAULT_CHANNEL_ORDER};
+
+#[test]
+fn bgra_is_default_order() {
+    assert_eq!(parse_channel_order("BGRA"), Ok(DEFAULT_CHANNEL_ORDER));
+}
+
+#[test]
+fn orders_are_parsed() {
+    assert_eq!(parse_channel_order("RGBA"), Ok([0, 1, 2, 3]));
+    assert_eq!(parse_channel_order("argb"), Ok([1, 2, 3, 0]));
+    assert_eq!(parse_channel_order("GRB"), Ok([1, 0, 2, 3]));
+}
+
+#[test]
+fn invalid_orders_are_rejected() {
+    for order in ["", "RG", "RGBAX", "RRGB", "RGBX", "RGA"] {
+        assert!(parse_channel_order(order).is_err(), "{order}");
+    }
+}
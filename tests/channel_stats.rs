@@ -0,0 +1,24 @@
+use simtricks::color::{channel_stats, ChannelStats, DEFAULT_CHANNEL_ORDER};
+
+#[test]
+fn channel_values_are_summarized() {
+    // BGRA LEDs: red and dark red, without any blue
+    let frame = vec![vec![[0, 0, 255, 255], [0, 10, 100, 255]]];
+    let [red, green, blue] = channel_stats(&frame, DEFAULT_CHANNEL_ORDER);
+    assert_eq!(
+        red,
+        ChannelStats {
+            min: 100,
+            max: 255,
+            mean: 177.5
+        }
+    );
+    assert_eq!((green.min, green.max, green.mean), (0, 10, 5.0));
+    assert_eq!(blue, ChannelStats::default());
+
+    // An empty frame has nothing to summarize
+    assert_eq!(
+        channel_stats(&[], DEFAULT_CHANNEL_ORDER),
+        [ChannelStats::default(); 3]
+    );
+}
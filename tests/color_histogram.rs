@@ -0,0 +1,36 @@
+use simtricks::color::{
+    color_histogram, histogram_bucket_color, most_common_colors, DEFAULT_CHANNEL_ORDER,
+};
+
+#[test]
+fn colors_are_counted_in_buckets() {
+    // BGRA LEDs: red, dark red, blue, and white
+    let frame = vec![
+        vec![[0, 0, 255, 255], [0, 0, 200, 255]],
+        vec![[255, 0, 0, 255], [255, 255, 255, 0]],
+    ];
+    let counts = color_histogram(&frame, DEFAULT_CHANNEL_ORDER, 2);
+    assert_eq!(counts.len(), 8);
+    assert_eq!(counts[0b100], 2);
+    assert_eq!(counts[0b001], 1);
+    assert_eq!(counts[0b111], 1);
+    assert_eq!(counts.iter().sum::<usize>(), 4);
+}
+
+#[test]
+fn bucket_colors_are_in_the_middle_of_each_range() {
+    assert_eq!(histogram_bucket_color(0, 2), [64, 64, 64]);
+    assert_eq!(histogram_bucket_color(0b100, 2), [192, 64, 64]);
+    assert_eq!(histogram_bucket_color(0b011, 2), [64, 192, 192]);
+}
+
+#[test]
+fn most_common_colors_are_counted_in_rgba_order() {
+    let red = [0, 0, 255, 255];
+    let blue = [255, 0, 0, 255];
+    let frame = vec![vec![red, blue, red], vec![[0; 4], red, blue]];
+    assert_eq!(
+        most_common_colors(&frame, DEFAULT_CHANNEL_ORDER, 2),
+        vec![([255, 0, 0, 255], 3), ([0, 0, 255, 255], 2)]
+    );
+}
@@ -0,0 +1,45 @@
+use simtricks::color::load_lut;
+use std::path::PathBuf;
+
+/// Write a lookup table file with the given contents, returning its path
+fn write_lut(name: &str, lut: serde_json::Value) -> PathBuf {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, lut.to_string()).unwrap();
+    path
+}
+
+#[test]
+fn lut_is_loaded() {
+    let identity: Vec<u8> = (0..=255).collect();
+    let inverted: Vec<u8> = (0..=255).rev().collect();
+    let path = write_lut(
+        "simtricks_lut_test.json",
+        serde_json::json!({"red": identity, "green": inverted, "blue": vec![7; 256]}),
+    );
+
+    let lut = load_lut(&path).unwrap();
+    assert_eq!(lut[0][10], 10);
+    assert_eq!(lut[1][10], 245);
+    assert_eq!(lut[2][10], 7);
+}
+
+#[test]
+fn invalid_luts_are_rejected() {
+    let identity: Vec<u16> = (0..=255).collect();
+    for (name, lut) in [
+        (
+            "simtricks_short_lut_test.json",
+            serde_json::json!({"red": identity[..255], "green": identity, "blue": identity}),
+        ),
+        (
+            "simtricks_missing_lut_test.json",
+            serde_json::json!({"red": identity, "green": identity}),
+        ),
+        (
+            "simtricks_out_of_range_lut_test.json",
+            serde_json::json!({"red": identity, "green": identity, "blue": vec![256; 256]}),
+        ),
+    ] {
+        assert!(load_lut(&write_lut(name, lut)).is_err(), "{name}");
+    }
+}
@@ -0,0 +1,46 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use simtricks::matrix_config::MatrixConfiguration;
+use simtricks::plugin::{PluginOptions, PluginSource};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Line a test plugin uses to include the shared `$copy` function
+const COPY_INCLUDE: &str = ";; include copy.wat";
+
+/// Get the path of a file in `tests/plugins`
+pub fn test_plugin_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("plugins")
+        .join(name)
+}
+
+/// Get the source of the test plugin with the given file name, from `tests/plugins`
+///
+/// A plugin that includes the shared `$copy` function is written out with the function spliced in, and loaded from
+/// there.
+pub fn test_plugin(name: &str) -> PluginSource {
+    let path = test_plugin_path(name);
+    let wat = fs::read_to_string(&path).unwrap();
+    if !wat.contains(COPY_INCLUDE) {
+        return PluginSource::File(path);
+    }
+
+    // Write to a file of its own first, so that tests loading the same plugin at once never see it half written
+    let copy = fs::read_to_string(test_plugin_path("include").join("copy.wat")).unwrap();
+    let spliced = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let partial = spliced.with_extension(format!("{:?}.partial", std::thread::current().id()));
+    fs::write(&partial, wat.replace(COPY_INCLUDE, &copy)).unwrap();
+    fs::rename(&partial, &spliced).unwrap();
+    PluginSource::File(spliced)
+}
+
+/// Options for running the test plugin with the given file name on a matrix of the given size
+pub fn test_plugin_options(name: &str, width: usize, height: usize) -> PluginOptions {
+    PluginOptions::new(
+        test_plugin(name),
+        MatrixConfiguration::new(width, height, 30.0),
+    )
+}
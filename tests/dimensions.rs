@@ -0,0 +1,16 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::plugin::{create_plugin, preferred_dimensions};
+
+#[test]
+fn declared_dimensions_are_read() {
+    let mut plugin = create_plugin(&test_plugin_options("dimensions.wat", 2, 2)).unwrap();
+    assert_eq!(preferred_dimensions(&mut plugin), Some((4, 3)));
+}
+
+#[test]
+fn missing_dimensions_export_is_none() {
+    let mut plugin = create_plugin(&test_plugin_options("cycle.wat", 2, 2)).unwrap();
+    assert_eq!(preferred_dimensions(&mut plugin), None);
+}
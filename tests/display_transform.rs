@@ -0,0 +1,207 @@
+use clap::ValueEnum;
+use simtricks::display_transform::{DisplayTransform, Origin, Rotation};
+
+/// A 3x2 matrix, where each LED is labelled with its own position
+const DIMENSIONS: (usize, usize) = (3, 2);
+
+/// Build the displayed matrix by looking up the frame position of every displayed LED
+fn displayed(transform: DisplayTransform) -> Vec<Vec<(usize, usize)>> {
+    let (width, height) = transform.displayed_dimensions(DIMENSIONS);
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| transform.frame_position((x, y), DIMENSIONS))
+                .collect()
+        })
+        .collect()
+}
+
+fn rotated(rotation: Rotation) -> DisplayTransform {
+    DisplayTransform {
+        rotation,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn no_rotation_is_identity() {
+    assert_eq!(
+        displayed(DisplayTransform::default()),
+        vec![vec![(0, 0), (1, 0), (2, 0)], vec![(0, 1), (1, 1), (2, 1)],]
+    );
+}
+
+#[test]
+fn rotate_90() {
+    assert_eq!(
+        displayed(rotated(Rotation::Clockwise90)),
+        vec![
+            vec![(0, 1), (0, 0)],
+            vec![(1, 1), (1, 0)],
+            vec![(2, 1), (2, 0)],
+        ]
+    );
+}
+
+#[test]
+fn rotate_180() {
+    assert_eq!(
+        displayed(rotated(Rotation::Clockwise180)),
+        vec![vec![(2, 1), (1, 1), (0, 1)], vec![(2, 0), (1, 0), (0, 0)],]
+    );
+}
+
+#[test]
+fn rotate_270() {
+    assert_eq!(
+        displayed(rotated(Rotation::Clockwise270)),
+        vec![
+            vec![(2, 0), (2, 1)],
+            vec![(1, 0), (1, 1)],
+            vec![(0, 0), (0, 1)],
+        ]
+    );
+}
+
+#[test]
+fn flip_h_and_flip_v() {
+    assert_eq!(
+        displayed(DisplayTransform {
+            flip_h: true,
+            ..Default::default()
+        }),
+        vec![vec![(2, 0), (1, 0), (0, 0)], vec![(2, 1), (1, 1), (0, 1)],]
+    );
+    assert_eq!(
+        displayed(DisplayTransform {
+            flip_v: true,
+            ..Default::default()
+        }),
+        vec![vec![(0, 1), (1, 1), (2, 1)], vec![(0, 0), (1, 0), (2, 0)],]
+    );
+}
+
+#[test]
+fn flip_is_applied_before_rotation() {
+    assert_eq!(
+        displayed(DisplayTransform {
+            rotation: Rotation::Clockwise90,
+            flip_h: true,
+            flip_v: false,
+            origin: Origin::TopLeft,
+        }),
+        vec![
+            vec![(2, 1), (2, 0)],
+            vec![(1, 1), (1, 0)],
+            vec![(0, 1), (0, 0)],
+        ]
+    );
+}
+
+#[test]
+fn rotation_from_degrees() {
+    assert_eq!(Rotation::from_degrees(0), Some(Rotation::None));
+    assert_eq!(Rotation::from_degrees(90), Some(Rotation::Clockwise90));
+    assert_eq!(Rotation::from_degrees(180), Some(Rotation::Clockwise180));
+    assert_eq!(Rotation::from_degrees(270), Some(Rotation::Clockwise270));
+    assert_eq!(Rotation::from_degrees(45), None);
+}
+
+#[test]
+fn rotations_are_named_by_their_degrees() {
+    for &rotation in Rotation::value_variants() {
+        let name = rotation.degrees().to_string();
+        assert_eq!(Rotation::from_str(&name, false), Ok(rotation));
+    }
+    assert!(Rotation::from_str("45", false).is_err());
+}
+
+#[test]
+fn apply_matches_frame_positions() {
+    // Give each LED of a 3x2 frame a distinct color based on its position
+    let frame: Vec<Vec<[u8; 4]>> = (0..DIMENSIONS.1)
+        .map(|y| {
+            (0..DIMENSIONS.0)
+                .map(|x| [x as u8, y as u8, 0, 255])
+                .collect()
+        })
+        .collect();
+
+    for rotation in [
+        Rotation::None,
+        Rotation::Clockwise90,
+        Rotation::Clockwise180,
+        Rotation::Clockwise270,
+    ] {
+        let expected: Vec<Vec<[u8; 4]>> = displayed(rotated(rotation))
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(x, y)| [x as u8, y as u8, 0, 255])
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rotated(rotation).apply(&frame), expected);
+    }
+}
+
+#[test]
+fn chain_matches_apply() {
+    let frame: Vec<Vec<[u8; 4]>> = (0..DIMENSIONS.1)
+        .map(|y| {
+            (0..DIMENSIONS.0)
+                .map(|x| [x as u8, y as u8, 0, 255])
+                .collect()
+        })
+        .collect();
+
+    for rotation in [
+        Rotation::None,
+        Rotation::Clockwise90,
+        Rotation::Clockwise180,
+        Rotation::Clockwise270,
+    ] {
+        for (flip_h, flip_v) in [(false, false), (true, false), (false, true), (true, true)] {
+            for &origin in Origin::value_variants() {
+                let transform = DisplayTransform {
+                    rotation,
+                    flip_h,
+                    flip_v,
+                    origin,
+                };
+                assert_eq!(transform.chain()(&frame), transform.apply(&frame));
+            }
+        }
+    }
+}
+
+#[test]
+fn flips_mirror_frames() {
+    let frame = vec![vec![[1; 4], [2; 4]], vec![[3; 4], [4; 4]]];
+    let flip = |flip_h, flip_v| DisplayTransform {
+        flip_h,
+        flip_v,
+        ..Default::default()
+    };
+    assert_eq!(
+        flip(true, false).apply(&frame),
+        vec![vec![[2; 4], [1; 4]], vec![[4; 4], [3; 4]]]
+    );
+    assert_eq!(
+        flip(false, true).apply(&frame),
+        vec![vec![[3; 4], [4; 4]], vec![[1; 4], [2; 4]]]
+    );
+}
+
+#[test]
+fn bottom_left_origin_flips_vertically() {
+    let origin = DisplayTransform {
+        origin: Origin::from_str("bottom-left", true).unwrap(),
+        ..Default::default()
+    };
+    let flip = DisplayTransform {
+        flip_v: true,
+        ..Default::default()
+    };
+    assert_eq!(displayed(origin), displayed(flip));
+}
@@ -0,0 +1,25 @@
+use simtricks::frame_server::FrameServer;
+use std::sync::{Arc, Mutex};
+use tungstenite::connect;
+
+#[test]
+fn clients_receive_frames() {
+    let frame = Arc::new(Mutex::new(Arc::new(vec![vec![[1, 2, 3, 255]; 2]; 2])));
+    let mut server = FrameServer::start(0, Arc::clone(&frame)).unwrap();
+    let (mut client, _) = connect(format!("ws://127.0.0.1:{}", server.port())).unwrap();
+
+    // The current frame is sent on connection
+    let message: serde_json::Value =
+        serde_json::from_str(&client.read().unwrap().into_text().unwrap()).unwrap();
+    assert_eq!(message["index"], 0);
+    assert_eq!(message["frame"], serde_json::json!(**frame.lock().unwrap()));
+
+    // New frames are broadcast
+    *frame.lock().unwrap() = Arc::new(vec![vec![[4, 5, 6, 255]; 2]; 2]);
+    let message: serde_json::Value =
+        serde_json::from_str(&client.read().unwrap().into_text().unwrap()).unwrap();
+    assert_eq!(message["index"], 1);
+    assert_eq!(message["frame"], serde_json::json!(**frame.lock().unwrap()));
+
+    server.shutdown();
+}
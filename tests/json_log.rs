@@ -0,0 +1,40 @@
+use log::{Level, LevelFilter, Log, Record};
+use simtricks::json_log::{json_log_line, JsonLogger};
+
+#[test]
+fn logs_are_formatted_as_json() {
+    let line = json_log_line(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("simtricks::plugin")
+            .args(format_args!("Plugin said \"hi\""))
+            .build(),
+        1234,
+    );
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&line).unwrap(),
+        serde_json::json!({
+            "level": "WARN",
+            "target": "simtricks::plugin",
+            "msg": "Plugin said \"hi\"",
+            "ts": 1234,
+        })
+    );
+}
+
+#[test]
+fn logs_are_filtered_by_module() {
+    let logger = JsonLogger::new()
+        .with_module_level("simtricks", LevelFilter::Info)
+        .with_module_level("matricks", LevelFilter::Debug);
+    let enabled = |level: Level, target: &str| {
+        logger.enabled(&log::Metadata::builder().level(level).target(target).build())
+    };
+
+    assert!(enabled(Level::Info, "simtricks"));
+    assert!(enabled(Level::Info, "simtricks::plugin_thread"));
+    assert!(!enabled(Level::Debug, "simtricks::plugin_thread"));
+    assert!(enabled(Level::Debug, "matricks"));
+    assert!(!enabled(Level::Error, "simtricksextra"));
+    assert!(!enabled(Level::Error, "extism"));
+}
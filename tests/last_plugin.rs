@@ -0,0 +1,30 @@
+use simtricks::last_plugin::{read_last_plugins, write_last_plugins};
+use std::fs;
+
+#[test]
+fn plugins_are_remembered_with_absolute_paths() {
+    let dir = std::env::temp_dir().join("simtricks_last_plugin_test");
+    let _ = fs::remove_dir_all(&dir);
+    let file = dir.join("state").join("last_plugin");
+
+    // Nothing is remembered until the file is written
+    assert!(read_last_plugins(&file).is_empty());
+
+    let plugins = vec![
+        String::from("plugins/rainbow.wasm"),
+        String::from("https://example.com/rainbow.wasm"),
+    ];
+    write_last_plugins(&file, &plugins).unwrap();
+    let remembered = read_last_plugins(&file);
+    assert_eq!(
+        remembered,
+        vec![
+            std::env::current_dir()
+                .unwrap()
+                .join("plugins/rainbow.wasm")
+                .display()
+                .to_string(),
+            String::from("https://example.com/rainbow.wasm"),
+        ]
+    );
+}
@@ -0,0 +1,51 @@
+use clap::ValueEnum;
+use simtricks::matrix_config::{MatrixConfiguration, RequestedDimensions, Wiring};
+
+#[test]
+fn frames_of_matrix_size_fit() {
+    let config = MatrixConfiguration::new(3, 2, 30.0);
+    assert!(config.fits(&config.blank_frame()));
+    assert!(!config.fits(&vec![vec![[0; 4]; 3]; 3]));
+    assert!(!config.fits(&[vec![[0; 4]; 3], vec![[0; 4]; 2]]));
+}
+
+#[test]
+fn small_frames_are_padded() {
+    let config = MatrixConfiguration::new(2, 2, 30.0);
+    let frame = vec![vec![[1; 4]]];
+    assert_eq!(
+        config.fit_frame(frame),
+        vec![vec![[1; 4], [0; 4]], vec![[0; 4], [0; 4]]]
+    );
+}
+
+#[test]
+fn large_frames_are_trimmed() {
+    let config = MatrixConfiguration::new(1, 1, 30.0);
+    let frame = vec![vec![[1; 4], [2; 4]], vec![[3; 4], [4; 4]]];
+    assert_eq!(config.fit_frame(frame), vec![vec![[1; 4]]]);
+}
+
+#[test]
+fn wiring_is_passed_to_plugin() {
+    let config = MatrixConfiguration {
+        wiring: Wiring::from_str("col-progressive", true).unwrap(),
+        ..MatrixConfiguration::new(2, 2, 30.0)
+    };
+    let plugin_config = config.to_plugin_config();
+    assert_eq!(plugin_config["serpentine"].as_deref(), Some("false"));
+    assert_eq!(plugin_config["wiring"].as_deref(), Some("col-progressive"));
+    assert_eq!(plugin_config["wiring_axis"].as_deref(), Some("columns"));
+    assert_eq!(config.wiring, Wiring::ColProgressive);
+}
+
+#[test]
+fn missing_dimension_is_taken_from_plugin() {
+    let width_only = RequestedDimensions {
+        width: Some(8),
+        height: None,
+    };
+    assert!(!width_only.is_complete());
+    assert_eq!(width_only.fill((4, 3)), (8, 3));
+    assert_eq!(width_only.or_default(), (8, 16));
+}
@@ -0,0 +1,19 @@
+use simtricks::color::{led_hex, palette, DEFAULT_CHANNEL_ORDER};
+
+#[test]
+fn palette_is_sorted_by_frequency() {
+    let red = [0, 0, 255, 255];
+    let blue = [255, 0, 0, 255];
+    let frame = vec![vec![red, blue, red], vec![[0; 4], red, blue]];
+    assert_eq!(
+        palette(&frame, DEFAULT_CHANNEL_ORDER, 2),
+        vec!["#FF0000FF", "#0000FFFF"]
+    );
+}
+
+#[test]
+fn led_hex_follows_channel_order() {
+    let orange = [0x10, 0x80, 0xFF, 0x40];
+    assert_eq!(led_hex(&orange, DEFAULT_CHANNEL_ORDER), "#FF8010");
+    assert_eq!(led_hex(&orange, [0, 1, 2, 3]), "#1080FF");
+}
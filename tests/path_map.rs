@@ -0,0 +1,74 @@
+use simtricks::path_map::{remove_staged_copies, PathMap};
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn maps_are_read_write_by_default() {
+    assert_eq!(
+        PathMap::parse("data>/data"),
+        PathMap {
+            local: PathBuf::from("data"),
+            plugin: PathBuf::from("/data"),
+            read_only: false,
+        }
+    );
+    assert_eq!(
+        PathMap::parse("data>/data:rw"),
+        PathMap::parse("data>/data")
+    );
+    assert_eq!(PathMap::parse("data").plugin, PathBuf::from("data"));
+}
+
+#[test]
+fn read_only_suffix_is_stripped() {
+    let map = PathMap::parse("fixtures>/fixtures:ro");
+    assert!(map.read_only);
+    assert_eq!(map.local, PathBuf::from("fixtures"));
+    assert_eq!(map.plugin, PathBuf::from("/fixtures"));
+}
+
+#[test]
+fn read_only_maps_are_staged_as_copies() {
+    let local = std::env::temp_dir().join(format!("simtricks_fixtures_{}", std::process::id()));
+    fs::create_dir_all(local.join("nested")).unwrap();
+    fs::write(local.join("nested").join("frame.json"), "[]").unwrap();
+
+    // A link back up the directory would make the copy go on forever if it were followed
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&local, local.join("nested").join("loop")).unwrap();
+
+    // A read-write map gives the plugin the original directory
+    let map = PathMap::parse(&format!("{}>/fixtures", local.display()));
+    assert_eq!(map.stage().unwrap(), local);
+
+    // A read-only map gives the plugin a copy, so writes to it leave the original alone
+    let map = PathMap::parse(&format!("{}>/fixtures:ro", local.display()));
+    let copy = map.stage().unwrap();
+    assert_ne!(copy, local);
+    assert!(fs::symlink_metadata(copy.join("nested").join("loop")).is_err());
+    assert_eq!(
+        fs::read_to_string(copy.join("nested").join("frame.json")).unwrap(),
+        "[]"
+    );
+    fs::write(copy.join("nested").join("frame.json"), "changed").unwrap();
+    assert_eq!(
+        fs::read_to_string(local.join("nested").join("frame.json")).unwrap(),
+        "[]"
+    );
+
+    // The copy is only made once, so later plugins are given the same copy
+    assert_eq!(map.stage().unwrap(), copy);
+
+    // A mapped file is copied too
+    let map = PathMap::parse(&format!(
+        "{}>/frame.json:ro",
+        local.join("nested").join("frame.json").display()
+    ));
+    let file_copy = map.stage().unwrap();
+    assert_eq!(fs::read_to_string(&file_copy).unwrap(), "[]");
+
+    fs::remove_dir_all(&local).unwrap();
+    remove_staged_copies();
+    assert!(!copy.exists());
+    assert!(!file_copy.exists());
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::plugin::{create_plugin, setup_plugin, PluginOptions};
+use simtricks::plugin_fps::is_valid_fps;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn requested_fps_is_recorded() {
+    let requested_fps = Arc::new(Mutex::new(None));
+    let options = PluginOptions {
+        requested_fps: Some(Arc::clone(&requested_fps)),
+        ..test_plugin_options("target_fps.wat", 2, 2)
+    };
+
+    let mut plugin = create_plugin(&options).unwrap();
+    setup_plugin(&mut plugin).unwrap();
+    assert_eq!(*requested_fps.lock().unwrap(), Some(60.0));
+}
+
+#[test]
+fn only_finite_positive_fps_are_valid() {
+    assert!(is_valid_fps(30.0));
+    assert!(is_valid_fps(0.5));
+    for fps in [0.0, -30.0, f32::NAN, f32::INFINITY] {
+        assert!(!is_valid_fps(fps), "{fps}");
+    }
+}
+
+#[test]
+fn zero_fps_is_rejected_on_the_command_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_simtricks"))
+        .args(["--headless", "--fps", "0", "--frames", "1", "--path"])
+        .arg(Path::new("tests").join("plugins").join("cycle.wat"))
+        .output()
+        .unwrap();
+
+    // Clap exits with 2 for a usage error, before the plugin is run
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--fps"));
+}
@@ -0,0 +1,20 @@
+use simtricks::plugin_frame::parse_frame;
+
+#[test]
+fn three_and_four_channel_leds_are_accepted() {
+    let frame = parse_frame("[[[1, 2, 3], [4, 5, 6, 7]]]").unwrap();
+    assert_eq!(frame, Some(vec![vec![[1, 2, 3, 255], [4, 5, 6, 7]]]));
+    assert_eq!(parse_frame("null").unwrap(), None);
+}
+
+#[test]
+fn other_led_lengths_are_rejected() {
+    for json in [
+        "[[[1, 2]]]",
+        "[[[1, 2, 3, 4, 5]]]",
+        "[[[256, 0, 0]]]",
+        "[[1]]",
+    ] {
+        assert!(parse_frame(json).is_err(), "{json}");
+    }
+}
@@ -0,0 +1,30 @@
+use simtricks::plugin_latency::{histogram, percentile, record_latency, MAX_LATENCY_SAMPLES};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[test]
+fn percentiles_use_nearest_rank() {
+    let samples: Vec<u128> = (1..=100).rev().collect();
+    assert_eq!(percentile(&samples, 50.0), Some(50));
+    assert_eq!(percentile(&samples, 95.0), Some(95));
+    assert_eq!(percentile(&samples, 99.0), Some(99));
+    assert_eq!(percentile(&samples, 100.0), Some(100));
+    assert_eq!(percentile(&[], 50.0), None);
+}
+
+#[test]
+fn oldest_samples_are_dropped() {
+    let samples = Mutex::new(Vec::new());
+    for micros in 0..MAX_LATENCY_SAMPLES as u64 + 5 {
+        record_latency(&samples, Duration::from_micros(micros));
+    }
+    let samples = samples.into_inner().unwrap();
+    assert_eq!(samples.len(), MAX_LATENCY_SAMPLES);
+    assert_eq!(samples[0], 5);
+}
+
+#[test]
+fn histogram_spans_every_sample() {
+    assert_eq!(histogram(&[0, 1, 2, 3, 9], 2), vec![4, 1]);
+    assert_eq!(histogram(&[], 3), vec![0, 0, 0]);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::test_plugin_options;
+use log::Level;
+use simtricks::headless::run_frames;
+use simtricks::plugin::PluginOptions;
+use simtricks::plugin_logs::{record_plugin_log, LogBreakpoint, PluginLogBuffer, MAX_PLUGIN_LOGS};
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+/// Options for running the logging test plugin, on a 1x1 matrix
+fn logs_plugin_options() -> PluginOptions {
+    test_plugin_options("logs.wat", 1, 1)
+}
+
+#[test]
+fn plugin_logs_are_kept_and_trigger_the_breakpoint() {
+    let log_buffer = PluginLogBuffer::default();
+    let log_breakpoint = LogBreakpoint::default();
+    log_breakpoint.enabled.store(true, Ordering::Relaxed);
+    let options = PluginOptions {
+        log_breakpoint: Some(log_breakpoint.clone()),
+        log_buffer: Some(log_buffer.clone()),
+        ..logs_plugin_options()
+    };
+
+    run_frames(&options, 1).unwrap();
+    assert_eq!(
+        log_buffer
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>(),
+        vec![
+            (Level::Info, String::from("hello")),
+            (Level::Warn, String::from("careful")),
+        ]
+    );
+    assert!(log_breakpoint.take());
+    assert!(!log_breakpoint.take());
+}
+
+#[test]
+fn oldest_logs_are_dropped() {
+    let log_buffer = PluginLogBuffer::default();
+    for i in 0..MAX_PLUGIN_LOGS + 2 {
+        record_plugin_log(&log_buffer, Level::Info, i.to_string());
+    }
+    let logs = log_buffer.lock().unwrap();
+    assert_eq!(logs.len(), MAX_PLUGIN_LOGS);
+    assert_eq!(logs.front().unwrap().1, "2");
+}
+
+#[test]
+fn plugin_logs_are_written_to_file() {
+    let path = std::env::temp_dir().join("simtricks_plugin_log_test.log");
+    let log_file = Arc::new(Mutex::new(BufWriter::new(File::create(&path).unwrap())));
+    let options = PluginOptions {
+        log_file: Some(log_file),
+        ..logs_plugin_options()
+    };
+
+    run_frames(&options, 2).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[INFO] hello\n[WARN] careful\n[INFO] hello\n[WARN] careful\n"
+    );
+}
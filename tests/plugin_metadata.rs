@@ -0,0 +1,24 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::plugin::{create_plugin, PluginMetadata};
+use simtricks::plugin_thread::FrameSource;
+
+#[test]
+fn name_export_names_the_plugin() {
+    let options = test_plugin_options("named.wat", 1, 1);
+    let mut plugin = create_plugin(&options).unwrap();
+    plugin.setup().unwrap();
+
+    // Without plugin_name, the name export is used, and missing exports are left out
+    let metadata = plugin.metadata();
+    assert_eq!(
+        metadata,
+        PluginMetadata {
+            name: Some(String::from("Rainbow")),
+            version: None,
+            author: None,
+        }
+    );
+    assert_eq!(metadata.describe().as_deref(), Some("Rainbow"));
+}
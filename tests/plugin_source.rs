@@ -0,0 +1,30 @@
+use simtricks::plugin::PluginSource;
+use std::sync::Arc;
+
+#[test]
+fn file_names_are_taken_from_paths_and_urls() {
+    for (source, file_name) in [
+        ("plugins/rainbow.wasm", "rainbow.wasm"),
+        ("rainbow.wasm", "rainbow.wasm"),
+        ("https://example.com/plugins/rainbow.wasm", "rainbow.wasm"),
+        (
+            "https://example.com/rainbow.wasm?version=2#top",
+            "rainbow.wasm",
+        ),
+        ("https://example.com/", "example.com"),
+    ] {
+        assert_eq!(
+            PluginSource::parse(source).file_name(),
+            file_name,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn piped_plugins_are_loaded_from_memory() {
+    let wasm_data = b"(module)".to_vec();
+    let source = PluginSource::Stdin(Arc::new(wasm_data.clone()));
+    assert_eq!(source.file_name(), "stdin");
+    assert_eq!(source.load().unwrap(), wasm_data);
+}
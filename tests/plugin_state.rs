@@ -0,0 +1,15 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::plugin::{create_plugin, load_state, save_state};
+
+#[test]
+fn missing_state_exports_are_errors() {
+    let options = test_plugin_options("cycle.wat", 2, 2);
+    let mut plugin = create_plugin(&options).unwrap();
+
+    let path = std::env::temp_dir().join("simtricks_missing_state_exports.bin");
+    assert!(save_state(&mut plugin, &path).is_err());
+    assert!(!path.exists());
+    assert!(load_state(&mut plugin, &path).is_err());
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::headless::run_frames;
+use simtricks::plugin::{create_plugin, PluginMetadata, PluginOptions};
+use simtricks::plugin_latency::LatencySamples;
+use simtricks::plugin_logs::LogBreakpoint;
+use simtricks::plugin_thread::{
+    plugin_thread, PluginCommand, PluginEvent, PluginState, PluginThreadConfig, PluginThreadState,
+    EVENT_CHANNEL_BOUND,
+};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Options for running the cycle test plugin, on a 2x2 matrix
+fn cycle_plugin_options() -> PluginOptions {
+    test_plugin_options("cycle.wat", 2, 2)
+}
+
+/// Drive the plugin from its own thread, returning the thread, its channels, and its update durations
+fn spawn_plugin_thread(
+    options: &PluginOptions,
+) -> (
+    JoinHandle<()>,
+    Sender<PluginCommand>,
+    Receiver<PluginEvent>,
+    LatencySamples,
+) {
+    let (command_sender, commands) = channel();
+    let (events, event_receiver) = sync_channel(EVENT_CHANNEL_BOUND);
+    let latency = LatencySamples::default();
+    let config = PluginThreadConfig {
+        matrix_config: options.matrix_config.clone(),
+        fps: 30.0,
+        warmup_frames: 0,
+        strict_setup: false,
+        strict_dimensions: false,
+        smoothing_frames: 1,
+        respect_plugin_fps: false,
+        pause_on_error: false,
+        capture: None,
+    };
+    let state = PluginThreadState {
+        commands,
+        events,
+        requested_fps: Arc::default(),
+        latency: Arc::clone(&latency),
+        log_breakpoint: options.log_breakpoint.clone(),
+        on_new_frame: None,
+    };
+    let plugin = create_plugin(options).unwrap();
+    let handle = thread::spawn(move || plugin_thread(plugin, config, state));
+    (handle, command_sender, event_receiver, latency)
+}
+
+#[test]
+fn frames_are_sent_for_each_step() {
+    let options = cycle_plugin_options();
+    let expected = run_frames(&options, 2).unwrap();
+    let (handle, command_sender, event_receiver, _) = spawn_plugin_thread(&options);
+
+    // The plugin's metadata is sent once it is set up, even though it provides none
+    let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event, PluginEvent::Metadata(PluginMetadata::default()));
+
+    // Each step should produce the next frame, and nothing else
+    for frame in expected {
+        command_sender.send(PluginCommand::Step(1)).unwrap();
+        let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(event, PluginEvent::FrameReady(frame));
+    }
+
+    // A stopped thread quits without sending anything more
+    command_sender.send(PluginCommand::Stop).unwrap();
+    handle.join().unwrap();
+    assert!(event_receiver.try_recv().is_err());
+}
+
+#[test]
+fn paused_thread_waits_for_commands() {
+    let (handle, command_sender, event_receiver, latency) =
+        spawn_plugin_thread(&cycle_plugin_options());
+
+    // Without autoplay or a step, the plugin is never updated, and nothing is sent after its metadata
+    let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(event, PluginEvent::Metadata(_)));
+    thread::sleep(Duration::from_millis(200));
+    assert!(latency.lock().unwrap().is_empty());
+    assert!(event_receiver.try_recv().is_err());
+
+    // The thread quits once the simulator stops listening to it
+    drop(command_sender);
+    handle.join().unwrap();
+}
+
+#[test]
+fn warn_log_pauses_autoplay_when_breaking_on_logs() {
+    let log_breakpoint = LogBreakpoint::default();
+    log_breakpoint.enabled.store(true, Ordering::Relaxed);
+    let options = PluginOptions {
+        log_breakpoint: Some(log_breakpoint),
+        ..test_plugin_options("logs.wat", 1, 1)
+    };
+    let (handle, command_sender, event_receiver, _) = spawn_plugin_thread(&options);
+    let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(event, PluginEvent::Metadata(_)));
+
+    // The plugin warns on its first update, which turns autoplay off before its frame is sent
+    command_sender
+        .send(PluginCommand::SetAutoplay(true))
+        .unwrap();
+    let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event, PluginEvent::AutoplayStopped(PluginState::Running));
+    let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(event, PluginEvent::FrameReady(_)));
+
+    // No more frames are made while paused
+    thread::sleep(Duration::from_millis(200));
+    assert!(event_receiver.try_recv().is_err());
+
+    drop(command_sender);
+    handle.join().unwrap();
+}
+
+#[test]
+fn thread_waits_for_the_simulator_to_read_its_frames() {
+    let (handle, command_sender, event_receiver, latency) =
+        spawn_plugin_thread(&test_plugin_options("named.wat", 1, 1));
+    let event = event_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(event, PluginEvent::Metadata(_)));
+
+    // Ask for more frames than fit in the channel, without reading any of them
+    command_sender
+        .send(PluginCommand::Step(EVENT_CHANNEL_BOUND * 2))
+        .unwrap();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(latency.lock().unwrap().len(), EVENT_CHANNEL_BOUND + 1);
+
+    // Every requested frame arrives once the simulator catches up
+    let frames = event_receiver
+        .iter()
+        .take(EVENT_CHANNEL_BOUND * 2)
+        .filter(|event| matches!(event, PluginEvent::FrameReady(_)))
+        .count();
+    assert_eq!(frames, EVENT_CHANNEL_BOUND * 2);
+
+    drop(command_sender);
+    handle.join().unwrap();
+}
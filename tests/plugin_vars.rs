@@ -0,0 +1,19 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::headless::run_frames;
+use simtricks::plugin::PluginOptions;
+
+#[test]
+fn vars_are_passed_to_the_plugin_config() {
+    let options = PluginOptions {
+        vars: vec![(String::from("frame"), String::from("[[[1,2,3,4]]]"))],
+        ..test_plugin_options("vars.wat", 1, 1)
+    };
+
+    // The plugin echoes the frame it was given
+    assert_eq!(
+        run_frames(&options, 1).unwrap(),
+        vec![vec![vec![[1, 2, 3, 4]]]]
+    );
+}
@@ -0,0 +1,58 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Produces three frames for a 2x2 matrix, cycling the colors of the LEDs, and then signals that it is done.
+(module
+  (import "env" "extism_alloc" (func $alloc (param i64) (result i64)))
+  (import "env" "extism_store_u8" (func $store_u8 (param i64 i32)))
+  (import "env" "extism_output_set" (func $output_set (param i64 i64)))
+  (memory (export "memory") 1)
+
+  ;; Frame 0
+  (data (i32.const 0) "[[[255,0,0,255],[0,255,0,255]],[[0,0,255,255],[0,0,0,255]]]")
+  ;; Frame 1
+  (data (i32.const 59) "[[[0,255,0,255],[0,0,255,255]],[[0,0,0,255],[255,0,0,255]]]")
+  ;; Frame 2
+  (data (i32.const 118) "[[[0,0,255,255],[0,0,0,255]],[[255,0,0,255],[0,255,0,255]]]")
+  ;; End of updates
+  (data (i32.const 177) "null")
+
+  ;; Number of updates made so far
+  (global $step (mut i32) (i32.const 0))
+
+  ;; Copy a string from this module's memory to the plugin output
+  (func $output (param $ptr i32) (param $len i32)
+    (local $offset i64)
+    (local $i i32)
+    (local.set $offset (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $offset) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $offset) (i64.extend_i32_u (local.get $len))))
+
+  (func (export "setup") (result i32)
+    (global.set $step (i32.const 0))
+    (i32.const 0))
+
+  (func (export "update") (result i32)
+    (if (i32.eq (global.get $step) (i32.const 0))
+      (then
+        (global.set $step (i32.add (global.get $step) (i32.const 1)))
+        (call $output (i32.const 0) (i32.const 59))
+        (return (i32.const 0))))
+    (if (i32.eq (global.get $step) (i32.const 1))
+      (then
+        (global.set $step (i32.add (global.get $step) (i32.const 1)))
+        (call $output (i32.const 59) (i32.const 59))
+        (return (i32.const 0))))
+    (if (i32.eq (global.get $step) (i32.const 2))
+      (then
+        (global.set $step (i32.add (global.get $step) (i32.const 1)))
+        (call $output (i32.const 118) (i32.const 59))
+        (return (i32.const 0))))
+    (call $output (i32.const 177) (i32.const 4))
+    (i32.const 0)))
@@ -0,0 +1,39 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Declares that it was designed for a 4x3 matrix, and produces no frames.
+(module
+  (import "env" "extism_alloc" (func $alloc (param i64) (result i64)))
+  (import "env" "extism_store_u8" (func $store_u8 (param i64 i32)))
+  (import "env" "extism_output_set" (func $output_set (param i64 i64)))
+  (memory (export "memory") 1)
+
+  ;; Preferred dimensions
+  (data (i32.const 0) "{\"width\": 4, \"height\": 3}")
+  ;; End of updates
+  (data (i32.const 25) "null")
+
+  ;; Copy a string from this module's memory to the plugin output
+  (func $output (param $ptr i32) (param $len i32)
+    (local $offset i64)
+    (local $i i32)
+    (local.set $offset (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $offset) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (call $output_set (local.get $offset) (i64.extend_i32_u (local.get $len))))
+
+  (func (export "dimensions") (result i32)
+    (call $output (i32.const 0) (i32.const 25))
+    (i32.const 0))
+
+  (func (export "setup") (result i32)
+    (i32.const 0))
+
+  (func (export "update") (result i32)
+    (call $output (i32.const 25) (i32.const 4))
+    (i32.const 0)))
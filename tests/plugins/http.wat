@@ -0,0 +1,25 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Makes an HTTP request to http://example.com/ on each update, then produces a blank frame.
+(module
+  (import "env" "extism_alloc" (func $alloc (param i64) (result i64)))
+  (import "env" "extism_store_u8" (func $store_u8 (param i64 i32)))
+  (import "env" "extism_length" (func $length (param i64) (result i64)))
+  (import "env" "extism_http_request" (func $http_request (param i64 i64) (result i64)))
+  (import "env" "extism_output_set" (func $output_set (param i64 i64)))
+  (memory (export "memory") 1)
+
+  (data (i32.const 0) "{\"url\":\"http://example.com/\"}")
+  (data (i32.const 64) "[[[0,0,0,0]]]")
+
+  ;; include copy.wat
+
+  (func (export "setup") (result i32)
+    (i32.const 0))
+
+  (func (export "update") (result i32)
+    (local $frame i64)
+    (drop (call $http_request (call $copy (i32.const 0) (i32.const 29)) (i64.const 0)))
+    (local.set $frame (call $copy (i32.const 64) (i32.const 13)))
+    (call $output_set (local.get $frame) (call $length (local.get $frame)))
+    (i32.const 0)))
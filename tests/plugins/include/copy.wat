@@ -0,0 +1,17 @@
+;; The $copy function shared by the test plugins, spliced into each plugin in place of a `;; include copy.wat` line.
+;;
+;; The plugin must import extism_alloc as $alloc and extism_store_u8 as $store_u8.
+  ;; Copy a string from this module's memory to a new block of plugin memory, returning its offset
+  (func $copy (param $ptr i32) (param $len i32) (result i64)
+    (local $offset i64)
+    (local $i i32)
+    (local.set $offset (call $alloc (i64.extend_i32_u (local.get $len))))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (call $store_u8
+          (i64.add (local.get $offset) (i64.extend_i32_u (local.get $i)))
+          (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $copy)))
+    (local.get $offset))
@@ -0,0 +1,25 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Makes an info log and a warn log on each update, and then produces a single LED frame.
+(module
+  (import "env" "extism_alloc" (func $alloc (param i64) (result i64)))
+  (import "env" "extism_store_u8" (func $store_u8 (param i64 i32)))
+  (import "env" "extism_output_set" (func $output_set (param i64 i64)))
+  (import "env" "matricks_info" (func $info (param i64)))
+  (import "env" "matricks_warn" (func $warn (param i64)))
+  (memory (export "memory") 1)
+
+  (data (i32.const 0) "[[[0,0,0,255]]]")
+  (data (i32.const 15) "hello")
+  (data (i32.const 20) "careful")
+
+  ;; include copy.wat
+
+  (func (export "setup") (result i32)
+    (i32.const 0))
+
+  (func (export "update") (result i32)
+    (call $info (call $copy (i32.const 15) (i32.const 5)))
+    (call $warn (call $copy (i32.const 20) (i32.const 7)))
+    (call $output_set (call $copy (i32.const 0) (i32.const 15)) (i64.const 15))
+    (i32.const 0)))
@@ -0,0 +1,10 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Requests a target FPS of 60 during setup, and produces no frames.
+(module
+  (import "env" "matricks_set_target_fps" (func $set_target_fps (param f32)))
+  (memory (export "memory") 1)
+
+  (func (export "setup") (result i32)
+    (call $set_target_fps (f32.const 60))
+    (i32.const 0)))
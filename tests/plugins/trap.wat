@@ -0,0 +1,11 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Sets up successfully, and then crashes on its first update.
+(module
+  (memory (export "memory") 1)
+
+  (func (export "setup") (result i32)
+    (i32.const 0))
+
+  (func (export "update") (result i32)
+    (unreachable)))
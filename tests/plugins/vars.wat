@@ -0,0 +1,23 @@
+;; A tiny Matricks plugin used to test Simtricks.
+;;
+;; Produces the frame given as the "frame" key of its config on each update.
+(module
+  (import "env" "extism_alloc" (func $alloc (param i64) (result i64)))
+  (import "env" "extism_store_u8" (func $store_u8 (param i64 i32)))
+  (import "env" "extism_length" (func $length (param i64) (result i64)))
+  (import "env" "extism_config_get" (func $config_get (param i64) (result i64)))
+  (import "env" "extism_output_set" (func $output_set (param i64 i64)))
+  (memory (export "memory") 1)
+
+  (data (i32.const 0) "frame")
+
+  ;; include copy.wat
+
+  (func (export "setup") (result i32)
+    (i32.const 0))
+
+  (func (export "update") (result i32)
+    (local $frame i64)
+    (local.set $frame (call $config_get (call $copy (i32.const 0) (i32.const 5))))
+    (call $output_set (local.get $frame) (call $length (local.get $frame)))
+    (i32.const 0)))
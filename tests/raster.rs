@@ -0,0 +1,22 @@
+use simtricks::color::DEFAULT_CHANNEL_ORDER;
+use simtricks::raster::rasterize_frame;
+
+#[test]
+fn leds_become_blocks_of_rgba_pixels() {
+    // One blue LED and one red LED, in BGRA order
+    let frame = vec![vec![[255, 0, 0, 255], [0, 0, 255, 255]]];
+    let image = rasterize_frame(&frame, DEFAULT_CHANNEL_ORDER, 2);
+
+    assert_eq!((image.width, image.height), (4, 2));
+    let blue = [0, 0, 255, 255];
+    let red = [255, 0, 0, 255];
+    let row = [blue, blue, red, red].concat();
+    assert_eq!(image.pixels, [row.clone(), row].concat());
+}
+
+#[test]
+fn empty_frame_gives_empty_image() {
+    let image = rasterize_frame(&[], DEFAULT_CHANNEL_ORDER, 4);
+    assert_eq!((image.width, image.height), (0, 0));
+    assert!(image.pixels.is_empty());
+}
@@ -0,0 +1,34 @@
+use image::codecs::gif::GifDecoder;
+use image::AnimationDecoder;
+use simtricks::color::DEFAULT_CHANNEL_ORDER;
+use simtricks::recording::Recording;
+use std::fs::File;
+
+#[test]
+fn recording_is_saved_as_gif() {
+    let mut recording = Recording::new();
+    assert!(recording.is_empty());
+    recording.push(vec![vec![[255, 0, 0, 255], [0; 4]]]);
+    recording.push(vec![vec![[0; 4], [0, 255, 0, 255]]]);
+
+    let path = std::env::temp_dir().join("simtricks_recording_test.gif");
+    recording.save_gif(DEFAULT_CHANNEL_ORDER, 4, &path).unwrap();
+
+    let frames = GifDecoder::new(File::open(&path).unwrap())
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].buffer().dimensions(), (8, 4));
+}
+
+#[test]
+fn empty_recording_is_not_saved() {
+    let path = std::env::temp_dir().join("simtricks_empty_recording_test.gif");
+    let _ = std::fs::remove_file(&path);
+    assert!(Recording::new()
+        .save_gif(DEFAULT_CHANNEL_ORDER, 4, &path)
+        .is_err());
+    assert!(!path.exists());
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::headless::run_frames;
+use simtricks::plugin::{denied_host, sandbox_hint};
+
+#[test]
+fn refused_host_is_found_in_plugin_error() {
+    let options = test_plugin_options("http.wat", 1, 1);
+
+    // The plugin asks for http://example.com/, which isn't allowed
+    let error = run_frames(&options, 1).unwrap_err().to_string();
+    assert_eq!(denied_host(&error), Some("example.com"));
+    assert!(sandbox_hint(&error)
+        .unwrap()
+        .contains("--allow-host example.com"));
+}
+
+#[test]
+fn denied_host_strips_the_url() {
+    let host =
+        |url: &str| denied_host(&format!("HTTP request to {url} is not allowed")).map(String::from);
+    assert_eq!(
+        host("https://user@api.example.com:8080/v1?q=1").as_deref(),
+        Some("api.example.com")
+    );
+    assert_eq!(host("http://[::1]:80/").as_deref(), Some("::1"));
+    assert_eq!(denied_host("Invalid update returned from plugin"), None);
+}
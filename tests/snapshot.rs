@@ -0,0 +1,135 @@
+mod common;
+
+use common::test_plugin_options;
+use simtricks::capture::CAPTURE_LED_SIZE;
+use simtricks::color::DEFAULT_CHANNEL_ORDER;
+use simtricks::frame_dump::{load_frames, FramePlayer};
+use simtricks::headless::{dump_frames, export_raw, run_frames, run_json_lines, save_single_frame};
+use simtricks::plugin_thread::FrameSource;
+use std::path::PathBuf;
+
+/// Load a stored snapshot of plugin output
+fn load_snapshot(name: &str) -> Vec<Vec<Vec<[u8; 4]>>> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(name);
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn cycle_plugin_matches_snapshot() {
+    let frames = run_frames(&test_plugin_options("cycle.wat", 2, 2), 3).unwrap();
+    assert_eq!(frames, load_snapshot("cycle.json"));
+}
+
+#[test]
+fn cycle_plugin_frame_at_step_matches_snapshot() {
+    let frames = run_frames(&test_plugin_options("cycle.wat", 2, 2), 2).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1], load_snapshot("cycle.json")[1]);
+}
+
+#[test]
+fn cycle_plugin_stops_when_done() {
+    let frames = run_frames(&test_plugin_options("cycle.wat", 2, 2), 10).unwrap();
+    assert_eq!(frames.len(), 3);
+}
+
+#[test]
+fn cycle_plugin_json_lines_match_snapshot() {
+    let mut output = Vec::new();
+    run_json_lines(
+        &test_plugin_options("cycle.wat", 2, 2),
+        1000.0,
+        None,
+        &mut output,
+    )
+    .unwrap();
+
+    let snapshot = load_snapshot("cycle.json");
+    let lines: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), snapshot.len());
+    for (index, (line, frame)) in lines.iter().zip(snapshot).enumerate() {
+        assert_eq!(line["index"], index);
+        assert_eq!(line["frame"], serde_json::json!(frame));
+    }
+}
+
+#[test]
+fn crashing_plugin_fails_json_lines() {
+    let mut output = Vec::new();
+    let result = run_json_lines(
+        &test_plugin_options("trap.wat", 2, 2),
+        1000.0,
+        None,
+        &mut output,
+    );
+    assert!(!result.unwrap_err().to_string().is_empty());
+    assert!(output.is_empty());
+}
+
+#[test]
+fn single_frame_is_saved_as_png() {
+    let path = std::env::temp_dir().join("simtricks_single_frame_test.png");
+    let _ = std::fs::remove_file(&path);
+    save_single_frame(
+        &test_plugin_options("cycle.wat", 2, 2),
+        DEFAULT_CHANNEL_ORDER,
+        &path,
+    )
+    .unwrap();
+
+    // The first LED of the first frame is blue in BGRA order
+    let image = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(
+        image.dimensions(),
+        (2 * CAPTURE_LED_SIZE as u32, 2 * CAPTURE_LED_SIZE as u32)
+    );
+    assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+}
+
+#[test]
+fn cycle_plugin_raw_export_matches_snapshot() {
+    let mut output = Vec::new();
+    let written = export_raw(
+        &test_plugin_options("cycle.wat", 2, 2),
+        DEFAULT_CHANNEL_ORDER,
+        10,
+        &mut output,
+    )
+    .unwrap();
+
+    // The plugin stops after three frames, each written as its index and then its BGRA LEDs
+    assert_eq!(written, 3);
+    let mut expected = Vec::new();
+    for (index, frame) in load_snapshot("cycle.json").iter().enumerate() {
+        expected.extend((index as u32).to_le_bytes());
+        expected.extend(frame.iter().flatten().flatten());
+    }
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn cycle_plugin_dumped_frames_replay_snapshot() {
+    let path = std::env::temp_dir().join(format!("simtricks_dump_{}.json", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    let dumped = dump_frames(&test_plugin_options("cycle.wat", 2, 2), None, file).unwrap();
+    let frames = load_frames(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let snapshot = load_snapshot("cycle.json");
+    assert_eq!(dumped, snapshot.len());
+    assert_eq!(frames, snapshot);
+
+    let mut player = FramePlayer::new(frames);
+    assert_eq!(player.dimensions(), Some((2, 2)));
+    for frame in &snapshot {
+        assert_eq!(player.update().unwrap().as_ref(), Some(frame));
+    }
+    assert_eq!(player.update().unwrap(), None);
+}
@@ -0,0 +1,73 @@
+use clap::ValueEnum;
+use simtricks::color::DEFAULT_CHANNEL_ORDER;
+use simtricks::plugin_thread::FrameSource;
+use simtricks::test_pattern::{PatternPlayer, TestPattern};
+
+#[test]
+fn patterns_fill_the_matrix() {
+    for &pattern in TestPattern::value_variants() {
+        let frame = pattern.frame(3, 2, [10, 20, 30, 255], DEFAULT_CHANNEL_ORDER, 0);
+        assert_eq!(frame.len(), 2, "{}", pattern.name());
+        assert!(frame.iter().all(|row| row.len() == 3), "{}", pattern.name());
+    }
+}
+
+#[test]
+fn patterns_are_in_the_given_channel_order() {
+    // BGRA LEDs
+    let color = [10, 20, 30, 40];
+    let solid = TestPattern::Solid.frame(2, 2, color, DEFAULT_CHANNEL_ORDER, 0);
+    assert!(solid.iter().flatten().all(|led| *led == [30, 20, 10, 40]));
+
+    let checkerboard = TestPattern::Checkerboard.frame(2, 2, color, DEFAULT_CHANNEL_ORDER, 0);
+    assert_eq!(
+        checkerboard,
+        vec![
+            vec![[30, 20, 10, 40], [0, 0, 0, 255]],
+            vec![[0, 0, 0, 255], [30, 20, 10, 40]],
+        ]
+    );
+}
+
+#[test]
+fn ramps_run_from_left_to_right() {
+    let ramp = TestPattern::RedRamp.frame(3, 1, [0; 4], DEFAULT_CHANNEL_ORDER, 0);
+    assert_eq!(
+        ramp[0],
+        vec![[0, 0, 0, 255], [0, 0, 127, 255], [0, 0, 255, 255]]
+    );
+
+    let gradient = TestPattern::Gradient.frame(3, 1, [200, 100, 0, 255], DEFAULT_CHANNEL_ORDER, 0);
+    assert_eq!(gradient[0][0], [0, 0, 0, 255]);
+    assert_eq!(gradient[0][2], [0, 100, 200, 255]);
+}
+
+#[test]
+fn patterns_are_found_by_name() {
+    let from_name = |name| TestPattern::from_str(name, true).ok();
+    assert_eq!(from_name("white"), Some(TestPattern::Solid));
+    assert_eq!(from_name("checker"), Some(TestPattern::Checkerboard));
+    assert_eq!(from_name("RGB-bars"), Some(TestPattern::RgbBars));
+    assert_eq!(from_name("red-ramp"), Some(TestPattern::RedRamp));
+    assert_eq!(from_name("plaid"), None);
+}
+
+#[test]
+fn sweep_moves_with_each_frame() {
+    let mut player = PatternPlayer {
+        pattern: TestPattern::Sweep,
+        dimensions: (3, 1),
+        color: [255, 255, 255, 255],
+        channel_order: DEFAULT_CHANNEL_ORDER,
+        step: 0,
+    };
+    player.setup().unwrap();
+
+    // The lit column moves one LED to the right each frame, wrapping back around to the left
+    for lit in [0, 1, 2, 0] {
+        let frame = player.update().unwrap().unwrap();
+        for (x, led) in frame[0].iter().enumerate() {
+            assert_eq!(led[0] == 255, x == lit, "column {x} of frame lit at {lit}");
+        }
+    }
+}